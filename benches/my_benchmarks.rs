@@ -29,11 +29,10 @@ use criterion::black_box;
 use std::collections::HashMap;
 use std::thread;
 use git2::{Repository, Diff};
+use commit_analysis::ReferenceExtractor;
 
 use rayon::prelude::*;
 
-use commit_analysis::CommitChanges;
-
 fn extract_pr_from_commit_message_alternative(commit_message: &str) -> Option<&str> {
     let pr_re: Regex = Regex::new(r"\(#(\d+)\)").unwrap();
 
@@ -42,11 +41,15 @@ fn extract_pr_from_commit_message_alternative(commit_message: &str) -> Option<&s
     })
 }
 
-fn extract_component_name_from_diff_summary(filename: &String) -> Option<String> {
+fn extract_pr_from_commit_message(commit_message: &str) -> bool {
+    commit_message.contains("(#")
+}
+
+fn extract_component_name_from_diff_summary(filename: &str) -> Option<String> {
     lazy_static! {
         static ref CN_RE: Regex = Regex::new(r"([\w-]+)/.+").unwrap();
     }
-    CN_RE.captures(&filename).map(|component_name| {
+    CN_RE.captures(filename).map(|component_name| {
         component_name.get(1).unwrap().as_str().to_owned()
     })
 }
@@ -60,7 +63,7 @@ fn extract_language_from_diff_summary(filename: &str) -> Option<String> {
     lazy_static! {
         static ref LANG_RE: Regex = Regex::new(r"\.(\w+)$").unwrap();
     }
-    LANG_RE.captures(&filename).map(|lang_name| {
+    LANG_RE.captures(filename).map(|lang_name| {
         lang_name.get(1).unwrap().as_str().to_owned()
     })
 }
@@ -70,7 +73,7 @@ fn extract_language_from_diff_summary2(filename: &str) -> Option<String> {
     Some(name_parts[name_parts.len() -1].to_owned())
 }
 
-fn extract_changes() -> CommitChanges {
+fn extract_changes() -> usize {
     let repo = Repository::open(".").unwrap();
     let mut revwalk = repo.revwalk().unwrap();
     revwalk.push_head().unwrap();
@@ -88,6 +91,7 @@ fn extract_changes() -> CommitChanges {
         diff
     }).collect();
     let stats = diffs.first().unwrap().stats().unwrap();
+    stats.files_changed()
 }
 
 fn revwalk() {
@@ -107,12 +111,12 @@ fn revwalk() {
         let diff = repo.diff_tree_to_tree(a.as_ref(), Some(&b), None).unwrap();
         diff
     }).collect();
-    let d = diffs.first().unwrap();
+    let _d = diffs.first().unwrap();
 }
 
 fn extract_names_and_sum(diff_files: Vec<String>) {
     let component_name_occurrences: Vec<String> = diff_files.par_iter().map(|file_name| {
-        extract_component_name_from_diff_summary(&file_name).unwrap_or_else(|| "unknown".to_owned())
+        extract_component_name_from_diff_summary(file_name).unwrap_or_else(|| "unknown".to_owned())
     }).collect();
 
     let comp_name_thread = thread::spawn(|| {
@@ -123,7 +127,7 @@ fn extract_names_and_sum(diff_files: Vec<String>) {
         component_map
     });
 
-    comp_name_thread.join();
+    comp_name_thread.join().unwrap();
 }
 
 fn extract_names_and_sum2(diff_files: Vec<String>) {
@@ -137,7 +141,7 @@ fn extract_names_and_sum2(diff_files: Vec<String>) {
         component_map
     });
 
-    comp_name_thread.join();
+    comp_name_thread.join().unwrap();
 }
 
 fn manipulate_hashmap(mm: &mut HashMap<String, u32>, cn: String) {
@@ -147,22 +151,39 @@ fn manipulate_hashmap(mm: &mut HashMap<String, u32>, cn: String) {
 
 fn bench_extract_component_name_from_diff_summary(c: &mut Criterion) {
     c.bench_function("extract component name",
-                     |b| b.iter(|| extract_component_name_from_diff_summary(black_box(&"component-a/src/test/java/Thing.java".to_owned()))));
+                     |b| b.iter(|| extract_component_name_from_diff_summary(black_box("component-a/src/test/java/Thing.java"))));
 }
 
 fn bench_extract_component_name_from_diff_summary2(c: &mut Criterion) {
     c.bench_function("extract component name2",
-                     |b| b.iter(|| extract_component_name_from_diff_summary2(black_box(&"component-a/src/test/java/Thing.java".to_owned()))));
+                     |b| b.iter(|| extract_component_name_from_diff_summary2(black_box("component-a/src/test/java/Thing.java"))));
 }
 
 fn bench_extract_language_from_diff_summary(c: &mut Criterion) {
     c.bench_function("extract language",
-                     |b| b.iter(|| extract_language_from_diff_summary(black_box(&"component-a/src/test/java/Thing.java".to_owned()))));
+                     |b| b.iter(|| extract_language_from_diff_summary(black_box("component-a/src/test/java/Thing.java"))));
 }
 
 fn bench_extract_language_from_diff_summary2(c: &mut Criterion) {
     c.bench_function("extract language2",
-                     |b| b.iter(|| extract_language_from_diff_summary2(black_box(&"component-a/src/test/java/Thing.java".to_owned()))));
+                     |b| b.iter(|| extract_language_from_diff_summary2(black_box("component-a/src/test/java/Thing.java"))));
+}
+
+fn bench_pr_from_commit_message_alternative(c: &mut Criterion) {
+    c.bench_function("extract pr alternative",
+                     |b| b.iter(|| extract_pr_from_commit_message_alternative(black_box("Add the thing (#1234)"))));
+}
+
+fn bench_pr_from_commit_message(c: &mut Criterion) {
+    c.bench_function("extract pr",
+                     |b| b.iter(|| extract_pr_from_commit_message(black_box("Add the thing (#1234)"))));
+}
+
+fn bench_reference_extractor(c: &mut Criterion) {
+    let mut extractor = ReferenceExtractor::new();
+    extractor.add_jira_projects(&["ABC".to_owned()]).unwrap();
+    c.bench_function("extract references",
+                     move |b| b.iter(|| extractor.extract(black_box("Add the thing (#1234)\n\nFixes #1200, ABC-12"))));
 }
 
 fn bench_hashmap(c: &mut Criterion) {
@@ -174,13 +195,13 @@ fn bench_hashmap(c: &mut Criterion) {
 
 fn bench_extract_changes(c: &mut Criterion) {
     c.bench_function("extract_changes",
-                     move |b| b.iter(|| extract_changes()));
+                     move |b| b.iter(extract_changes));
 
 }
 
 fn bench_revwalk(c: &mut Criterion) {
     c.bench_function("revwalk",
-                     move |b| b.iter(|| revwalk()));
+                     move |b| b.iter(revwalk));
 }
 
 fn bench_extract_names_and_sum_v1(c: &mut Criterion) {
@@ -259,6 +280,7 @@ criterion_group!(benches,
     bench_extract_language_from_diff_summary2,
     bench_pr_from_commit_message_alternative,
     bench_pr_from_commit_message,
+    bench_reference_extractor,
     bench_extract_names_and_sum_v1,
     bench_extract_names_and_sum_v2);
 
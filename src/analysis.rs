@@ -0,0 +1,1353 @@
+/*
+   Copyright 2019-2020 foamdino@gmail.com
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
+use git2::{BlameOptions, Commit, Delta, Diff, DiffFindOptions, ObjectType, Oid, Patch, Repository, RevparseMode, Revwalk, Signature, Sort, Time, Tree};
+use chrono::{DateTime, Datelike, Duration, Timelike, Utc};
+
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::{AuthorStats, CodeAge, CommitChanges, ComponentResolver, DirectoryDepth, Error, FileStats, Mailmap, PathPattern, ReferenceExtractor, Skipped, Stats, TopLevelDir};
+use crate::cache::Cache;
+use crate::coupling::record_co_changes;
+use crate::{convert_git_time_to_datetime, Granularity, LanguageRegistry, Timezone};
+
+const EMPTY_CHANGES: CommitChanges = CommitChanges::new(0, 0, 0);
+const ALL_REFS: &str = "--all";
+const MAILMAP_FILE: &str = ".mailmap";
+const DEFAULT_SIMILARITY: u16 = 50;
+const DEFAULT_TOP_N: usize = 20;
+// bulk changes such as reformatting couple everything with everything
+const MAX_COUPLED_FILES: usize = 50;
+
+/// How commits with more than one parent are counted.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MergeStrategy {
+    /// Only walk the first parent of merges, so a merged branch shows up as
+    /// the merge commit alone, diffed against the first parent.
+    FirstParent,
+    /// Walk every commit and diff merges against their first parent, counting
+    /// them as ordinary commits. A change made on a merged branch is counted
+    /// twice, once for the branch commit and again in the merge's diff.
+    DiffFirstParent,
+    /// Walk every commit but keep merges out of the ordinary counts, their
+    /// diffs against the first parent are summed in `Stats::merge_changes`.
+    /// Each change is counted once, for the commit which made it, and a PR
+    /// named by a merge is sized by the merge's diff.
+    #[default]
+    Separate,
+    /// Leave merges out of everything but `Stats::num_merges` and the PRs and
+    /// issues they name, without diffing them.
+    Skip,
+}
+
+impl FromStr for MergeStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "first-parent" => Ok(MergeStrategy::FirstParent),
+            "diff-first-parent" => Ok(MergeStrategy::DiffFirstParent),
+            "separate" => Ok(MergeStrategy::Separate),
+            "skip" => Ok(MergeStrategy::Skip),
+            _ => Err(format!("unknown merge strategy: {} (expected first-parent, diff-first-parent, separate or skip)", s))
+        }
+    }
+}
+
+impl fmt::Display for MergeStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            MergeStrategy::FirstParent => "first-parent",
+            MergeStrategy::DiffFirstParent => "diff-first-parent",
+            MergeStrategy::Separate => "separate",
+            MergeStrategy::Skip => "skip",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Settings controlling how the history of a repository is analysed.
+///
+/// The defaults reproduce the behaviour of `walk_entire_history`; each setter
+/// consumes and returns the options so they can be chained.
+#[derive(Debug, Clone)]
+pub struct AnalysisOptions {
+    revisions: Vec<String>,
+    merge_strategy: MergeStrategy,
+    references: ReferenceExtractor,
+    include_paths: Vec<PathPattern>,
+    exclude_paths: Vec<PathPattern>,
+    languages: Option<Vec<String>>,
+    language_registry: LanguageRegistry,
+    components: Arc<dyn ComponentResolver>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    timezone: Timezone,
+    granularity: Granularity,
+    use_mailmap: bool,
+    alias_file: Option<PathBuf>,
+    line_stats: bool,
+    file_coupling: bool,
+    find_renames: bool,
+    find_copies: bool,
+    similarity: u16,
+    lenient: bool,
+    verbose: bool,
+    jobs: usize,
+    top_n: usize,
+    code_age_revision: Option<String>,
+    cache_file: Option<PathBuf>,
+}
+
+impl Default for AnalysisOptions {
+    fn default() -> Self {
+        AnalysisOptions {
+            revisions: vec![],
+            merge_strategy: MergeStrategy::default(),
+            references: ReferenceExtractor::default(),
+            include_paths: vec![],
+            exclude_paths: vec![],
+            languages: None,
+            language_registry: LanguageRegistry::default(),
+            components: Arc::new(TopLevelDir),
+            since: None,
+            until: None,
+            timezone: Timezone::default(),
+            granularity: Granularity::default(),
+            use_mailmap: true,
+            alias_file: None,
+            line_stats: true,
+            file_coupling: false,
+            find_renames: true,
+            find_copies: false,
+            similarity: DEFAULT_SIMILARITY,
+            lenient: false,
+            verbose: false,
+            jobs: 0,
+            top_n: DEFAULT_TOP_N,
+            code_age_revision: None,
+            cache_file: None,
+        }
+    }
+}
+
+impl AnalysisOptions {
+
+    pub fn new() -> AnalysisOptions {
+        AnalysisOptions::default()
+    }
+
+    /// Revisions to walk, in the form `git rev-list` accepts them: tips such as
+    /// `main`, exclusions such as `^release/1.x`, ranges such as `v1.2..v1.3` or
+    /// `a...b`, and `--all`. With no revisions only HEAD is walked.
+    pub fn revisions(mut self, revisions: Vec<String>) -> Self {
+        self.revisions = revisions;
+        self
+    }
+
+    /// Adds a single revision to walk, see `revisions`.
+    pub fn revision(mut self, revision: &str) -> Self {
+        self.revisions.push(revision.to_owned());
+        self
+    }
+
+    /// The timezone commits are bucketed by month, weekday and year in. The
+    /// author's own is used by default.
+    pub fn timezone(mut self, timezone: Timezone) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    /// The length of the periods commits, changes and churn are counted over
+    /// time by, a month by default.
+    pub fn granularity(mut self, granularity: Granularity) -> Self {
+        self.granularity = granularity;
+        self
+    }
+
+    /// How merge commits are walked and counted, see `MergeStrategy`.
+    pub fn merge_strategy(mut self, strategy: MergeStrategy) -> Self {
+        self.merge_strategy = strategy;
+        self
+    }
+
+    /// How PR and issue IDs are found in commit messages. A commit counts as a
+    /// PR when its message references one.
+    pub fn references(mut self, extractor: ReferenceExtractor) -> Self {
+        self.references = extractor;
+        self
+    }
+
+    /// Only files matching one of the included patterns are counted. Every
+    /// file is included when there are none.
+    pub fn include_path(mut self, pattern: PathPattern) -> Self {
+        self.include_paths.push(pattern);
+        self
+    }
+
+    /// Files matching an excluded pattern are not counted, even when they are
+    /// included, e.g. `vendor/`, `**/generated/**` or `*.lock`.
+    pub fn exclude_path(mut self, pattern: PathPattern) -> Self {
+        self.exclude_paths.push(pattern);
+        self
+    }
+
+    /// Languages counted in the language stats, by their canonical names such
+    /// as `Java` or `YAML`. Every language the registry detects is counted by default.
+    pub fn languages(mut self, languages: Vec<String>) -> Self {
+        self.languages = Some(languages);
+        self
+    }
+
+    /// How files are mapped to languages.
+    pub fn language_registry(mut self, registry: LanguageRegistry) -> Self {
+        self.language_registry = registry;
+        self
+    }
+
+    /// How changed files are mapped to components, the top level directory by default.
+    pub fn component_resolver<R: ComponentResolver + 'static>(mut self, resolver: R) -> Self {
+        self.components = Arc::new(resolver);
+        self
+    }
+
+    /// Number of leading directories making up a component name.
+    pub fn component_depth(self, depth: usize) -> Self {
+        self.component_resolver(DirectoryDepth::new(depth))
+    }
+
+    /// Only commits authored at or after this time are analysed.
+    ///
+    /// Bounds are compared with the instant the commit was authored, whatever
+    /// timezone it was authored in.
+    pub fn since(mut self, since: DateTime<Utc>) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    /// Only commits authored at or before this time are analysed.
+    pub fn until(mut self, until: DateTime<Utc>) -> Self {
+        self.until = Some(until);
+        self
+    }
+
+    /// Whether author identities are resolved through the repository's `.mailmap`.
+    pub fn use_mailmap(mut self, use_mailmap: bool) -> Self {
+        self.use_mailmap = use_mailmap;
+        self
+    }
+
+    /// Additional aliases in `.mailmap` format, these win over the repository's own.
+    pub fn alias_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.alias_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Whether lines inserted and deleted are counted. This generates a patch
+    /// for every changed file, which is most of the cost on large histories.
+    pub fn line_stats(mut self, line_stats: bool) -> Self {
+        self.line_stats = line_stats;
+        self
+    }
+
+    /// Whether pairs of files changed together are counted, on top of pairs of
+    /// components. Commits changing more than 50 files are left out.
+    pub fn file_coupling(mut self, file_coupling: bool) -> Self {
+        self.file_coupling = file_coupling;
+        self
+    }
+
+    /// Whether renamed files are detected, instead of counting a delete and an add.
+    pub fn find_renames(mut self, find_renames: bool) -> Self {
+        self.find_renames = find_renames;
+        self
+    }
+
+    /// Whether copied files are detected, instead of counting an add. Sources
+    /// of copies are only looked for among the files the commit modified.
+    pub fn find_copies(mut self, find_copies: bool) -> Self {
+        self.find_copies = find_copies;
+        self
+    }
+
+    /// How similar, in percent, two files must be to count as a rename or copy.
+    pub fn similarity(mut self, percent: u16) -> Self {
+        self.similarity = percent.min(100);
+        self
+    }
+
+    /// When set, commits and paths which can't be read are recorded in
+    /// `Stats::skipped` instead of failing the analysis.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// When set, the time spent walking, diffing and blaming is written to stderr.
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Number of worker threads diffing commits, 0 uses one per CPU.
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    /// Blames every counted file at `revision` once the history is analysed,
+    /// giving the age of the code that survives there, see `Stats::code_age`.
+    pub fn code_age(mut self, revision: &str) -> Self {
+        self.code_age_revision = Some(revision.to_owned());
+        self
+    }
+
+    /// Length of the ranked lists, such as `Stats::hotspots`.
+    pub fn top_n(mut self, top_n: usize) -> Self {
+        self.top_n = top_n;
+        self
+    }
+
+    /// File caching the stats between runs. A rerun with the same options only
+    /// walks the commits made since the cached run and merges them in.
+    pub fn cache_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.cache_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    // identifies the options and author aliases which affect the stats, so a
+    // cache written with different ones isn't reused
+    fn fingerprint(&self, mailmap: &Mailmap) -> String {
+        let mut opts = self.clone();
+        opts.jobs = 0;
+        // what's in the alias file is covered by the mailmap, wherever it lives
+        opts.alias_file = None;
+        opts.verbose = false;
+        // rankings are worked out again from the cached counts, and code age
+        // isn't cached at all
+        opts.top_n = 0;
+        opts.code_age_revision = None;
+        opts.cache_file = None;
+        format!("{:?} {}", opts, mailmap.fingerprint())
+    }
+
+    fn log_time(&self, stage: &str, started: Instant) {
+        if self.verbose {
+            eprintln!("{} time: {:?}", stage, started.elapsed());
+        }
+    }
+
+    fn skip_or_fail(&self, skipped: &mut Vec<Skipped>, commit: Option<Oid>, path: Option<String>, e: Error) -> Result<(), Error> {
+        if !self.lenient {
+            return Err(e);
+        }
+        skipped.push(Skipped::new(commit.map(|oid| oid.to_string()), path, e.to_string()));
+        Ok(())
+    }
+
+    fn load_mailmap(&self, repo: &Repository) -> Result<Mailmap, Error> {
+        let mut mailmap = Mailmap::new();
+        if self.use_mailmap {
+            if let Some(contents) = read_repo_mailmap(repo) {
+                mailmap.extend(Mailmap::parse(&contents));
+            }
+        }
+        if let Some(path) = &self.alias_file {
+            let contents = fs::read_to_string(path)?;
+            mailmap.extend(Mailmap::parse(&contents));
+        }
+        Ok(mailmap)
+    }
+
+    fn is_in_window(&self, git_time: &Time) -> Result<bool, Error> {
+        let dt = convert_git_time_to_datetime(git_time)?;
+        Ok(self.since.is_none_or(|since| dt >= since) && self.until.is_none_or(|until| dt <= until))
+    }
+
+    // a commit is never authored after it is committed, so once the committer
+    // time is before the window nothing further down a time sorted walk is in it.
+    // The slack covers clocks which disagree between machines.
+    fn is_before_window(&self, git_time: &Time) -> Result<bool, Error> {
+        match self.since {
+            Some(since) => Ok(convert_git_time_to_datetime(git_time)? + Duration::days(2) < since),
+            None => Ok(false)
+        }
+    }
+
+    fn is_counted_path(&self, file_name: &str) -> bool {
+        if !self.include_paths.is_empty() && !self.include_paths.iter().any(|p| p.matches(file_name)) {
+            return false;
+        }
+        !self.exclude_paths.iter().any(|p| p.matches(file_name))
+    }
+
+    fn is_counted_language(&self, lang_name: &str) -> bool {
+        self.languages.as_ref().is_none_or(|languages| languages.iter().any(|l| l == lang_name))
+    }
+
+}
+
+// pushes the revisions onto the walk, returning the tips it starts from
+fn push_revisions(repo: &Repository, revwalk: &mut Revwalk, revisions: &[String]) -> Result<Vec<Oid>, Error> {
+    let mut tips = vec![];
+    if revisions.is_empty() {
+        tips.push(repo.head()?.peel_to_commit()?.id());
+    }
+
+    for spec in revisions {
+        if spec == ALL_REFS {
+            for reference in repo.references()? {
+                // refs which don't point at a commit, e.g. tags of trees, have no history
+                if let Ok(commit) = reference?.peel_to_commit() {
+                    tips.push(commit.id());
+                }
+            }
+            tips.push(repo.head()?.peel_to_commit()?.id());
+        } else if let Some(excluded) = spec.strip_prefix('^') {
+            let hidden = repo.revparse_single(excluded)?.peel_to_commit()?;
+            revwalk.hide(hidden.id())?;
+        } else if spec.contains("..") {
+            let range = repo.revparse(spec)?;
+            let from = range.from().map(|o| o.peel_to_commit()).transpose()?;
+            let to = range.to().map(|o| o.peel_to_commit()).transpose()?;
+            if let Some(to) = &to {
+                tips.push(to.id());
+            }
+            if range.mode().contains(RevparseMode::MERGE_BASE) {
+                // a...b: everything reachable from either side but not from both
+                if let Some(from) = &from {
+                    tips.push(from.id());
+                    if let Some(to) = &to {
+                        revwalk.hide(repo.merge_base(from.id(), to.id())?)?;
+                    }
+                }
+            } else if let Some(from) = &from {
+                revwalk.hide(from.id())?;
+            }
+        } else {
+            tips.push(repo.revparse_single(spec)?.peel_to_commit()?.id());
+        }
+    }
+
+    tips.sort();
+    tips.dedup();
+    for tip in &tips {
+        revwalk.push(*tip)?;
+    }
+    Ok(tips)
+}
+
+// git reads the mailmap from the working tree, falling back to HEAD for bare repos
+fn read_repo_mailmap(repo: &Repository) -> Option<String> {
+    if let Some(workdir) = repo.workdir() {
+        return fs::read_to_string(workdir.join(MAILMAP_FILE)).ok();
+    }
+    let head = repo.head().ok()?.peel_to_tree().ok()?;
+    let entry = head.get_name(MAILMAP_FILE)?;
+    let blob = entry.to_object(repo).ok()?.peel_to_blob().ok()?;
+    Some(String::from_utf8_lossy(blob.content()).into_owned())
+}
+
+// identifies an author by their canonical `name <email>`
+fn author_name(mailmap: &Mailmap, sig: &Signature) -> String {
+    let (name, email) = mailmap.resolve(&String::from_utf8_lossy(sig.name_bytes()),
+                                        &String::from_utf8_lossy(sig.email_bytes()));
+    format!("{} <{}>", name, email)
+}
+
+// the first line of a text blob, shebangs are all that is read from file contents
+fn first_line_of_blob(repo: &Repository, id: Oid) -> Option<String> {
+    let blob = repo.find_blob(id).ok()?;
+    if blob.is_binary() || !blob.content().starts_with(b"#!") {
+        return None;
+    }
+    let content = blob.content();
+    let end = content.iter().position(|b| *b == b'\n').unwrap_or(content.len());
+    Some(String::from_utf8_lossy(&content[..end]).into_owned())
+}
+
+// lines inserted and deleted by the delta at `idx`, binary files have none
+fn line_stats(diff: &Diff, idx: usize) -> Result<(u32, u32), Error> {
+    match Patch::from_diff(diff, idx)? {
+        Some(patch) => {
+            let (_, inserted, deleted) = patch.line_stats()?;
+            Ok((inserted as u32, deleted as u32))
+        },
+        None => Ok((0, 0))
+    }
+}
+
+fn changes_for_delta(status: Delta) -> CommitChanges {
+    match status {
+        Delta::Added => CommitChanges::new(1, 0, 0),
+        Delta::Deleted => CommitChanges::new(0, 1, 0),
+        Delta::Modified => CommitChanges::new(0, 0, 1),
+        Delta::Renamed => EMPTY_CHANGES.with_moves(1, 0),
+        Delta::Copied => EMPTY_CHANGES.with_moves(0, 1),
+        _ => EMPTY_CHANGES
+    }
+}
+
+// a changed file which is counted in the stats
+struct FileDelta {
+    file_name: String,
+    // where a renamed or copied file came from
+    old_file_name: Option<String>,
+    lang_name: Option<String>,
+    status: Delta,
+    lines_inserted: u32,
+    lines_deleted: u32,
+}
+
+// a Repository handle and a partial result for each thread of a pool, as
+// libgit2 handles can't be shared between threads; the handles are opened on
+// first use
+struct Workers<T> {
+    repo_path: String,
+    slots: Vec<Mutex<(Option<Repository>, T)>>,
+}
+
+impl<T: Send> Workers<T> {
+
+    fn new(pool: &ThreadPool, repo_path: &str, empty: impl Fn() -> T) -> Workers<T> {
+        let slots = (0..pool.current_num_threads()).map(|_| Mutex::new((None, empty()))).collect();
+        Workers{repo_path: repo_path.to_owned(), slots}
+    }
+
+    // runs `f` with the handle and partial result of the current pool thread
+    fn with<F>(&self, f: F) -> Result<(), Error>
+        where F: FnOnce(&Repository, &mut T) -> Result<(), Error> {
+        let index = rayon::current_thread_index().unwrap_or(0);
+        let mut slot = self.slots[index].lock().unwrap_or_else(PoisonError::into_inner);
+        let (repo, result) = &mut *slot;
+        let repo = match repo {
+            Some(repo) => repo,
+            None => repo.insert(Repository::open(&self.repo_path)?)
+        };
+        f(repo, result)
+    }
+
+    fn into_results(self) -> impl Iterator<Item = T> {
+        self.slots.into_iter().map(|slot| slot.into_inner().unwrap_or_else(PoisonError::into_inner).1)
+    }
+}
+
+/// An analysis of the history of a single repository.
+pub struct Analysis {
+    repo_path: String,
+    options: AnalysisOptions,
+}
+
+impl Analysis {
+
+    pub fn new(repo_path: &str, options: AnalysisOptions) -> Analysis {
+        Analysis{repo_path: repo_path.to_owned(), options}
+    }
+
+    pub fn options(&self) -> &AnalysisOptions {
+        &self.options
+    }
+
+    // the counted files changed by `commit`, paths which can't be decoded are
+    // skipped when lenient
+    fn commit_deltas(&self, repo: &Repository, commit: &Commit, skipped: &mut Vec<Skipped>) -> Result<Vec<FileDelta>, Error> {
+        let opts = &self.options;
+        // merges are diffed against their first parent, the branch they were merged into
+        let a = if commit.parent_count() > 0 {
+            let parent = commit.parent(0)?;
+            Some(parent.tree()?)
+        } else {
+            None
+        };
+        let b = commit.tree()?;
+        let mut diff = repo.diff_tree_to_tree(a.as_ref(), Some(&b), None)?;
+        if opts.find_renames || opts.find_copies {
+            let mut find_opts = DiffFindOptions::new();
+            find_opts.renames(opts.find_renames)
+                .copies(opts.find_copies)
+                .rename_threshold(opts.similarity)
+                .copy_threshold(opts.similarity);
+            diff.find_similar(Some(&mut find_opts))?;
+        }
+
+        let mut deltas = vec![];
+        for (idx, d) in diff.deltas().enumerate() {
+            let file = d.new_file();
+            let file_name = match file.path().and_then(|p| p.to_str()) {
+                Some(file_name) => file_name.to_owned(),
+                None => {
+                    let lossy = file.path_bytes().map(|b| String::from_utf8_lossy(b).into_owned());
+                    let e = Error::Encoding(format!("path {} is not valid UTF-8", lossy.clone().unwrap_or_default()));
+                    opts.skip_or_fail(skipped, Some(commit.id()), lossy, e)?;
+                    continue;
+                }
+            };
+            // we should only consider files in the diff which are changes to the component code
+            if !opts.is_counted_path(&file_name) {
+                continue;
+            }
+            let (lines_inserted, lines_deleted) = if opts.line_stats {
+                line_stats(&diff, idx)?
+            } else {
+                (0, 0)
+            };
+            let lang_name = opts.language_registry.detect(&file_name)
+                .or_else(|| {
+                    // scripts without an extension, from whichever side of the diff still has the file
+                    let blob_id = if d.status() == Delta::Deleted { d.old_file().id() } else { file.id() };
+                    let first_line = first_line_of_blob(repo, blob_id)?;
+                    opts.language_registry.detect_shebang(&first_line)
+                })
+                .map(|l| l.to_owned());
+            let old_file_name = match d.status() {
+                Delta::Renamed | Delta::Copied => d.old_file().path().and_then(|p| p.to_str()).map(|p| p.to_owned()),
+                _ => None
+            };
+            deltas.push(FileDelta{file_name, old_file_name, lang_name, status: d.status(), lines_inserted, lines_deleted});
+        }
+        Ok(deltas)
+    }
+
+    pub fn run(&self) -> Result<Stats, Error> {
+        let opts = &self.options;
+        let repo = Repository::open(&self.repo_path)?;
+        let mailmap = opts.load_mailmap(&repo)?;
+        let mut stats = Stats::with_granularity(opts.granularity);
+
+        // with a usable cache only the commits since the cached tips are walked
+        let cached = match &opts.cache_file {
+            Some(path) => Cache::load(path, &opts.fingerprint(&mailmap))?,
+            None => None
+        };
+        let mut revwalk = repo.revwalk()?;
+        let tips = push_revisions(&repo, &mut revwalk, &opts.revisions)?;
+        if opts.merge_strategy == MergeStrategy::FirstParent {
+            revwalk.simplify_first_parent();
+        }
+        let cached = match cached {
+            Some(cache) if cache.is_ancestor_of(&repo, &tips)? => {
+                for tip in cache.tips()? {
+                    revwalk.hide(tip)?;
+                }
+                Some(cache)
+            },
+            _ => None
+        };
+
+        let before_revwalk = Instant::now();
+        let oids = self.commits_in_window(&repo, revwalk, &mut stats)?;
+        opts.log_time("Revwalk", before_revwalk);
+
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(opts.jobs)
+            .build()
+            .map_err(|e| Error::Config(format!("couldn't start worker threads: {}", e)))?;
+
+        // every pool thread diffs its share of the commits into its own Stats
+        let before_diffs = Instant::now();
+        let workers = Workers::new(&pool, &self.repo_path, || Stats::with_granularity(opts.granularity));
+        pool.install(|| {
+            oids.par_iter().try_for_each(|oid| {
+                workers.with(|worker_repo, worker_stats| self.analyse_commit(worker_repo, &mailmap, *oid, worker_stats))
+            })
+        })?;
+        for worker_stats in workers.into_results() {
+            stats.merge(worker_stats);
+        }
+        if let Some(cache) = cached {
+            stats.merge(cache.into_stats());
+        }
+        stats.merge_strategy = opts.merge_strategy;
+        stats.timezone = opts.timezone.to_string();
+        stats.summarise(opts.top_n, opts.components.as_ref());
+        stats.skipped.sort_by(|a, b| (a.commit(), a.path()).cmp(&(b.commit(), b.path())));
+        opts.log_time("Diff", before_diffs);
+
+        let mut stats = match &opts.cache_file {
+            Some(path) => Cache::save(path, opts.fingerprint(&mailmap), &tips, stats)?,
+            None => stats
+        };
+
+        if let Some(revision) = &opts.code_age_revision {
+            let before_blame = Instant::now();
+            stats.code_age = Some(self.code_age(&pool, &mailmap, revision, &mut stats.skipped)?);
+            stats.skipped.sort_by(|a, b| (a.commit(), a.path()).cmp(&(b.commit(), b.path())));
+            opts.log_time("Blame", before_blame);
+        }
+        Ok(stats)
+    }
+
+    // blames every counted file at `revision` on the worker pool
+    fn code_age(&self, pool: &ThreadPool, mailmap: &Mailmap, revision: &str, skipped: &mut Vec<Skipped>) -> Result<CodeAge, Error> {
+        let opts = &self.options;
+        let repo = Repository::open(&self.repo_path)?;
+        let commit = repo.revparse_single(revision)?.peel_to_commit()?;
+        let commit_id = commit.id();
+        let mut files = vec![];
+        self.tree_files(&repo, &commit.tree()?, "", commit_id, &mut files, skipped)?;
+        files.retain(|(path, _)| opts.is_counted_path(path));
+
+        let workers = Workers::new(pool, &self.repo_path, || (CodeAge::new(&commit_id.to_string()), vec![]));
+        pool.install(|| {
+            files.par_iter().try_for_each(|(path, blob_id)| {
+                workers.with(|worker_repo, (code_age, worker_skipped)| {
+                    if let Err(e) = self.blame_file(worker_repo, mailmap, commit_id, path, *blob_id, code_age) {
+                        opts.skip_or_fail(worker_skipped, Some(commit_id), Some(path.clone()), e)?;
+                    }
+                    Ok(())
+                })
+            })
+        })?;
+        let mut code_age = CodeAge::new(&commit_id.to_string());
+        for (worker_code_age, worker_skipped) in workers.into_results() {
+            code_age.merge(worker_code_age);
+            skipped.extend(worker_skipped);
+        }
+        Ok(code_age)
+    }
+
+    // every blob under `tree`, walked by hand as `Tree::walk` silently stops at
+    // the first name which isn't UTF-8
+    fn tree_files(&self, repo: &Repository, tree: &Tree, prefix: &str, commit_id: Oid,
+                  files: &mut Vec<(String, Oid)>, skipped: &mut Vec<Skipped>) -> Result<(), Error> {
+        for entry in tree.iter() {
+            let path = match std::str::from_utf8(entry.name_bytes()) {
+                Ok(name) => format!("{}{}", prefix, name),
+                Err(_) => {
+                    let lossy = format!("{}{}", prefix, String::from_utf8_lossy(entry.name_bytes()));
+                    let e = Error::Encoding(format!("path {} is not valid UTF-8", lossy));
+                    self.options.skip_or_fail(skipped, Some(commit_id), Some(lossy), e)?;
+                    continue;
+                }
+            };
+            match entry.kind() {
+                Some(ObjectType::Blob) => files.push((path, entry.id())),
+                Some(ObjectType::Tree) => {
+                    let subtree = repo.find_tree(entry.id())?;
+                    self.tree_files(repo, &subtree, &format!("{}/", path), commit_id, files, skipped)?;
+                },
+                // submodules have no blobs of their own
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn blame_file(&self, repo: &Repository, mailmap: &Mailmap, commit_id: Oid, path: &str, blob_id: Oid,
+                  code_age: &mut CodeAge) -> Result<(), Error> {
+        let opts = &self.options;
+        if repo.find_blob(blob_id)?.is_binary() {
+            return Ok(());
+        }
+        let lang_name = opts.language_registry.detect(path)
+            .or_else(|| opts.language_registry.detect_shebang(&first_line_of_blob(repo, blob_id)?))
+            .filter(|l| opts.is_counted_language(l));
+        let comp_name = opts.components.component(path).unwrap_or_else(|| "unknown".to_owned());
+
+        let mut blame_opts = BlameOptions::new();
+        blame_opts.newest_commit(commit_id);
+        let blame = repo.blame_file(Path::new(path), Some(&mut blame_opts))?;
+        // read every hunk first, so a file with an unreadable one isn't half counted
+        let mut hunks = vec![];
+        for hunk in blame.iter() {
+            let sig = hunk.final_signature();
+            let year = opts.timezone.local_time(&sig.when())?.year().to_string();
+            hunks.push((author_name(mailmap, &sig), year, hunk.lines_in_hunk() as u32));
+        }
+        for (author, year, lines) in hunks {
+            code_age.record_lines(&comp_name, lang_name, &author, &year, lines);
+        }
+        Ok(())
+    }
+
+    // the commits to analyse, newest first
+    fn commits_in_window(&self, repo: &Repository, mut revwalk: Revwalk, stats: &mut Stats) -> Result<Vec<Oid>, Error> {
+        let opts = &self.options;
+        if opts.since.is_some() {
+            // newest first, so the walk can stop once it is past the window
+            revwalk.set_sorting(Sort::TIME);
+        }
+
+        let mut oids = vec![];
+        for step in revwalk {
+            let oid = match step {
+                Ok(oid) => oid,
+                Err(e) => {
+                    opts.skip_or_fail(&mut stats.skipped, None, None, e.into())?;
+                    continue;
+                }
+            };
+            let commit = match repo.find_commit(oid) {
+                Ok(commit) => commit,
+                Err(e) => {
+                    opts.skip_or_fail(&mut stats.skipped, Some(oid), None, e.into())?;
+                    continue;
+                }
+            };
+            let (committed, authored) = (commit.committer().when(), commit.author().when());
+            match opts.is_before_window(&committed) {
+                Ok(true) => break,
+                Ok(false) => {},
+                Err(e) => {
+                    opts.skip_or_fail(&mut stats.skipped, Some(oid), None, e)?;
+                    continue;
+                }
+            }
+            match opts.is_in_window(&authored) {
+                Ok(true) => oids.push(oid),
+                Ok(false) => {},
+                Err(e) => opts.skip_or_fail(&mut stats.skipped, Some(oid), None, e)?
+            }
+        }
+        Ok(oids)
+    }
+
+    fn analyse_commit(&self, repo: &Repository, mailmap: &Mailmap, oid: Oid, stats: &mut Stats) -> Result<(), Error> {
+        let opts = &self.options;
+        let commit = match repo.find_commit(oid) {
+            Ok(commit) => commit,
+            Err(e) => return opts.skip_or_fail(&mut stats.skipped, Some(oid), None, e.into())
+        };
+        let author_sig = commit.author();
+        // the author's time in the analysis timezone, which dates are reported in
+        let local_dt = match opts.timezone.local_time(&author_sig.when()) {
+            Ok(local_dt) => local_dt,
+            Err(e) => return opts.skip_or_fail(&mut stats.skipped, Some(oid), None, e)
+        };
+
+        // a skipped merge isn't diffed at all
+        let is_merge = commit.parent_count() > 1;
+        let deltas = if is_merge && opts.merge_strategy == MergeStrategy::Skip {
+            vec![]
+        } else {
+            // diff first, so a commit which can't be read is left out entirely
+            match self.commit_deltas(repo, &commit, &mut stats.skipped) {
+                Ok(deltas) => deltas,
+                Err(e) => return opts.skip_or_fail(&mut stats.skipped, Some(oid), None, e)
+            }
+        };
+
+        // merges count towards the PRs and issues they name whatever the strategy
+        let references = opts.references.extract(&String::from_utf8_lossy(commit.message_bytes()));
+        let is_pr = !references.prs.is_empty();
+        for issue in references.issues {
+            *stats.issues.entry(issue).or_insert(0) += 1;
+        }
+        if is_pr {
+            stats.num_prs += 1;
+        }
+        let author_key = author_name(mailmap, &author_sig);
+
+        if is_merge {
+            stats.num_merges += 1;
+            if opts.merge_strategy == MergeStrategy::Separate || opts.merge_strategy == MergeStrategy::Skip {
+                // kept out of the file and component counts, but a merged PR
+                // is sized by the merge's diff
+                let mut merge_comps: HashSet<String> = HashSet::new();
+                let mut merge_files: Vec<String> = vec![];
+                let mut merge_changes = EMPTY_CHANGES;
+                for d in deltas {
+                    merge_changes += changes_for_delta(d.status).with_lines(d.lines_inserted, d.lines_deleted);
+                    merge_comps.insert(opts.components.component(&d.file_name).unwrap_or_else(|| "unknown".to_owned()));
+                    merge_files.push(d.file_name);
+                }
+                stats.merge_changes += merge_changes;
+                for pr in references.prs {
+                    stats.prs.entry(pr).or_default().record_commit(&author_key, &merge_comps, &merge_files, merge_changes);
+                }
+                return Ok(());
+            }
+        }
+        stats.num_commits_to_master += 1;
+        if !is_pr {
+            stats.missing_prs += 1;
+        }
+
+        let author = stats.authors.entry(author_key.clone()).or_insert_with(|| AuthorStats::new(local_dt));
+        author.record_commit(local_dt, is_pr);
+
+        // record changes by time
+        stats.commits_over_time.record(local_dt.date_naive(), 1);
+        let commit_day = format!("{:?}", local_dt.weekday());
+        *stats.commits_by_day_of_week.entry(commit_day).or_insert(0) += 1;
+        stats.punchcard.record(local_dt.weekday(), local_dt.hour());
+
+        let mut local_langs: HashSet<String> = HashSet::new();
+        let mut local_comps: HashSet<String> = HashSet::new();
+        let mut commit_changes = EMPTY_CHANGES;
+        let pr_files: Vec<String> = if is_pr { deltas.iter().map(|d| d.file_name.clone()).collect() } else { vec![] };
+        if opts.file_coupling {
+            let changed: Vec<&String> = deltas.iter()
+                .filter(|d| d.status != Delta::Deleted)
+                .map(|d| &d.file_name)
+                .collect();
+            if changed.len() <= MAX_COUPLED_FILES {
+                record_co_changes(&mut stats.file_coupling, changed);
+            }
+        }
+        for d in deltas {
+            stats.num_file_changes += 1;
+            let changes = changes_for_delta(d.status).with_lines(d.lines_inserted, d.lines_deleted);
+            // a removal alone doesn't modify the file, hence the epoch
+            if d.status == Delta::Deleted {
+                stats.files.entry(d.file_name.clone()).or_insert_with(|| FileStats::new(DateTime::UNIX_EPOCH.fixed_offset()))
+                    .record_removal(local_dt);
+            } else {
+                stats.files.entry(d.file_name.clone()).or_insert_with(|| FileStats::new(local_dt))
+                    .record_change(&author_key, local_dt, changes);
+            }
+            if let (Delta::Renamed, Some(old_file_name)) = (d.status, &d.old_file_name) {
+                stats.files.entry(old_file_name.clone()).or_insert_with(|| FileStats::new(DateTime::UNIX_EPOCH.fixed_offset()))
+                    .record_removal(local_dt);
+            }
+            let comp_name = opts.components.component(&d.file_name).unwrap_or_else(|| "unknown".to_owned());
+
+            commit_changes += changes;
+            *stats.component_authors.entry(comp_name.clone()).or_default().entry(author_key.clone()).or_insert(0) += 1;
+            *stats.changes_by_component.entry(comp_name.clone()).or_insert(EMPTY_CHANGES) += changes;
+
+            if let Some(lang_name) = d.lang_name.filter(|l| opts.is_counted_language(l)) {
+                *stats.changes_by_lang.entry(lang_name.clone()).or_insert(EMPTY_CHANGES) += changes;
+
+                // only count the language once / diff
+                if !local_langs.contains(&lang_name) {
+                    author.record_lang(&lang_name);
+                    *stats.lang_stats.entry(lang_name.clone()).or_insert(0) += 1;
+                    local_langs.insert(lang_name);
+                }
+            }
+
+            if d.status == Delta::Renamed {
+                let old_comp_name = d.old_file_name.as_ref()
+                    .and_then(|f| opts.components.component(f))
+                    .unwrap_or_else(|| "unknown".to_owned());
+                if old_comp_name != comp_name {
+                    *stats.component_migrations.entry(old_comp_name).or_default()
+                        .entry(comp_name.clone()).or_insert(0) += 1;
+                }
+            }
+
+            // only count first occurrence of component / diff
+            if !local_comps.contains(&comp_name) {
+                author.record_component(&comp_name);
+                *stats.component_stats.entry(comp_name.clone()).or_insert(0) += 1;
+                local_comps.insert(comp_name);
+            }
+        }
+        stats.changes_over_time.record(local_dt.date_naive(), commit_changes);
+        stats.churn_over_time.record(local_dt.date_naive(), commit_changes.churn());
+        author.record_changes(commit_changes);
+        record_co_changes(&mut stats.component_coupling, &local_comps);
+        for comp_name in &local_comps {
+            stats.punchcards_by_component.entry(comp_name.clone()).or_default()
+                .record(local_dt.weekday(), local_dt.hour());
+        }
+        for pr in references.prs {
+            stats.prs.entry(pr).or_default().record_commit(&author_key, &local_comps, &pr_files, commit_changes);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+    use chrono::TimeZone;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const DAY: i64 = 86_400;
+    // 2020-01-06, a Monday
+    const START: i64 = 1_578_268_800;
+
+    static NEXT_REPO: AtomicUsize = AtomicUsize::new(0);
+
+    // a throwaway repository in the temp dir, removed again on drop
+    struct TestRepo {
+        path: PathBuf,
+        repo: Repository,
+    }
+
+    impl TestRepo {
+
+        fn new() -> TestRepo {
+            let path = std::env::temp_dir().join(format!("commit-analysis-test-{}-{}", process::id(),
+                                                         NEXT_REPO.fetch_add(1, Ordering::SeqCst)));
+            let repo = Repository::init(&path).unwrap();
+            TestRepo{path, repo}
+        }
+
+        fn path(&self) -> &str {
+            self.path.to_str().unwrap()
+        }
+
+        // writes, or with None removes, each file and commits on top of HEAD
+        fn commit(&self, files: &[(&str, Option<&str>)], message: &str, time: i64) -> Oid {
+            let mut index = self.repo.index().unwrap();
+            for (name, contents) in files {
+                let file = self.path.join(name);
+                match contents {
+                    Some(contents) => {
+                        fs::create_dir_all(file.parent().unwrap()).unwrap();
+                        fs::write(&file, contents).unwrap();
+                        index.add_path(Path::new(name)).unwrap();
+                    },
+                    None => {
+                        fs::remove_file(&file).unwrap();
+                        index.remove_path(Path::new(name)).unwrap();
+                    }
+                }
+            }
+            index.write().unwrap();
+            let tree = self.repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let parent = self.repo.head().ok().map(|head| head.peel_to_commit().unwrap());
+            let parents: Vec<&Commit> = parent.iter().collect();
+            let sig = Signature::new("Jane", "jane@example.com", &Time::new(time, 0)).unwrap();
+            self.repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents).unwrap()
+        }
+
+        // a commit on top of HEAD with a hand written author line, for times
+        // libgit2 won't write itself
+        fn commit_with_author(&self, author: &str, message: &str) -> Oid {
+            let head = self.repo.head().unwrap().peel_to_commit().unwrap();
+            let raw = format!("tree {}\nparent {}\nauthor {}\ncommitter Jane <jane@example.com> {} +0000\n\n{}\n",
+                              head.tree_id(), head.id(), author, START, message);
+            let oid = self.repo.odb().unwrap().write(ObjectType::Commit, raw.as_bytes()).unwrap();
+            self.repo.head().unwrap().set_target(oid, message).unwrap();
+            oid
+        }
+
+        // creates `name` at HEAD and checks it out
+        fn branch(&self, name: &str) {
+            let head = self.repo.head().unwrap().peel_to_commit().unwrap();
+            self.repo.branch(name, &head, false).unwrap();
+            self.checkout(name);
+        }
+
+        fn checkout(&self, name: &str) {
+            self.repo.set_head(&format!("refs/heads/{}", name)).unwrap();
+            self.repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force())).unwrap();
+        }
+
+        // merges `branch` into HEAD with a merge commit, as `git merge --no-ff` does
+        fn merge(&self, branch: &str, message: &str, time: i64) -> Oid {
+            let ours = self.repo.head().unwrap().peel_to_commit().unwrap();
+            let theirs = self.repo.find_branch(branch, git2::BranchType::Local).unwrap().get().peel_to_commit().unwrap();
+            let mut index = self.repo.merge_commits(&ours, &theirs, None).unwrap();
+            let tree = self.repo.find_tree(index.write_tree_to(&self.repo).unwrap()).unwrap();
+            let sig = Signature::new("Jane", "jane@example.com", &Time::new(time, 0)).unwrap();
+            let oid = self.repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &[&ours, &theirs]).unwrap();
+            self.repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force())).unwrap();
+            oid
+        }
+
+        fn run(&self, opts: AnalysisOptions) -> Result<Stats, Error> {
+            Analysis::new(self.path(), opts).run()
+        }
+
+        fn num_commits(&self, opts: AnalysisOptions) -> u32 {
+            self.run(opts).unwrap().num_commits_to_master()
+        }
+    }
+
+    impl Drop for TestRepo {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+
+    #[test]
+    fn test_include_and_exclude_paths() {
+        assert!(AnalysisOptions::default().is_counted_path("pom.xml"));
+
+        let opts = AnalysisOptions::default()
+            .include_path(PathPattern::new("*/").unwrap())
+            .exclude_path(PathPattern::new("vendor/").unwrap())
+            .exclude_path(PathPattern::new("**/generated/**").unwrap())
+            .exclude_path(PathPattern::new("*.lock").unwrap());
+        assert!(opts.is_counted_path("component-a/src/Thing.java"));
+        assert!(!opts.is_counted_path("pom.xml"));
+        assert!(!opts.is_counted_path("vendor/lib/a.go"));
+        assert!(!opts.is_counted_path("component-a/target/generated/Thing.java"));
+        assert!(!opts.is_counted_path("web/yarn.lock"));
+    }
+
+    #[test]
+    fn test_lenient_skips_out_of_range_times() {
+        let repo = TestRepo::new();
+        repo.commit(&[("svc/a.rs", Some("1\n"))], "first", START);
+        let bad = repo.commit_with_author("A <a@example.com> 99999999999999999 +0000", "far future");
+        assert!(repo.run(AnalysisOptions::new()).is_err());
+
+        let stats = repo.run(AnalysisOptions::new().lenient(true)).unwrap();
+        assert_eq!(stats.num_commits_to_master(), 1);
+        assert_eq!(stats.skipped().len(), 1);
+        assert_eq!(stats.skipped()[0].commit(), Some(bad.to_string().as_str()));
+    }
+
+    #[test]
+    fn test_cache_is_dropped_when_the_mailmap_changes() {
+        let repo = TestRepo::new();
+        repo.commit(&[("svc/a.rs", Some("1\n"))], "first", START);
+        let cache = repo.path.join(".git").join("stats.json");
+        repo.run(AnalysisOptions::new().cache_file(&cache)).unwrap();
+
+        fs::write(repo.path.join(MAILMAP_FILE), "Jane Doe <jane@example.com>\n").unwrap();
+        repo.commit(&[("svc/a.rs", Some("2\n"))], "second", START + DAY);
+        let stats = repo.run(AnalysisOptions::new().cache_file(&cache)).unwrap();
+        let authors: Vec<&String> = stats.authors().keys().collect();
+        assert_eq!(authors, vec!["Jane Doe <jane@example.com>"]);
+        assert_eq!(stats.authors()["Jane Doe <jane@example.com>"].num_commits(), 2);
+    }
+
+    #[test]
+    fn test_code_age_reports_undecodable_paths() {
+        let repo = TestRepo::new();
+        repo.commit(&[("svc/a.rs", Some("1\n2\n"))], "first", START);
+        // a directory sorting before svc/ whose name isn't UTF-8
+        let head = repo.repo.head().unwrap().peel_to_commit().unwrap();
+        let blob = repo.repo.blob(b"3\n").unwrap();
+        let mut dir = repo.repo.treebuilder(None).unwrap();
+        dir.insert("b.rs", blob, 0o100_644).unwrap();
+        let dir = dir.write().unwrap();
+        let mut root = repo.repo.treebuilder(Some(&head.tree().unwrap())).unwrap();
+        root.insert(b"a\xfe".to_vec(), dir, 0o040_000).unwrap();
+        let tree = repo.repo.find_tree(root.write().unwrap()).unwrap();
+        let sig = Signature::new("Jane", "jane@example.com", &Time::new(START + DAY, 0)).unwrap();
+        repo.repo.commit(Some("HEAD"), &sig, &sig, "odd dir", &tree, &[&head]).unwrap();
+
+        let stats = repo.run(AnalysisOptions::new().code_age("HEAD").lenient(true)).unwrap();
+        assert_eq!(stats.code_age().unwrap().by_component()["svc"].lines(), 2);
+        assert!(stats.skipped().iter().any(|s| s.path() == Some("a\u{fffd}")));
+    }
+
+    #[test]
+    fn test_line_counts() {
+        let repo = TestRepo::new();
+        repo.commit(&[("svc/a.rs", Some("1\n2\n3\n")), ("lib/l.py", Some("1\n2\n"))], "add", START);
+        // one line changed and one removed in a.rs, one line added to l.py
+        repo.commit(&[("svc/a.rs", Some("1\nx\n")), ("lib/l.py", Some("1\n2\n3\n"))], "edit", START + 40 * DAY);
+
+        let stats = repo.run(AnalysisOptions::new()).unwrap();
+        let lines = |changes: &CommitChanges| (changes.lines_inserted(), changes.lines_deleted());
+        assert_eq!(lines(&stats.changes_by_component()["svc"]), (4, 2));
+        assert_eq!(lines(&stats.changes_by_component()["lib"]), (3, 0));
+        assert_eq!(lines(&stats.changes_by_lang()["Rust"]), (4, 2));
+        assert_eq!(lines(&stats.changes_by_lang()["Python"]), (3, 0));
+        let churn: Vec<(String, u32)> = stats.churn_over_time().labelled().collect();
+        assert_eq!(churn, vec![("2020-01".to_owned(), 5), ("2020-02".to_owned(), 4)]);
+
+        let stats = repo.run(AnalysisOptions::new().line_stats(false)).unwrap();
+        assert_eq!(lines(&stats.changes_by_component()["svc"]), (0, 0));
+        assert_eq!(lines(&stats.changes_by_lang()["Python"]), (0, 0));
+        assert_eq!(stats.changes_by_component()["svc"].files_modified(), 1);
+        assert!(stats.churn_over_time().labelled().all(|(_, churn)| churn == 0));
+    }
+
+    #[test]
+    fn test_revisions_ranges_and_exclusions() {
+        let repo = TestRepo::new();
+        repo.commit(&[("svc/a.rs", Some("1\n"))], "root", START);
+        repo.branch("feature");
+        repo.commit(&[("lib/l.py", Some("1\n"))], "feature 1", START + DAY);
+        repo.commit(&[("lib/l.py", Some("2\n"))], "feature 2", START + 2 * DAY);
+        repo.checkout("master");
+        repo.commit(&[("svc/a.rs", Some("2\n"))], "master 1", START + 3 * DAY);
+        repo.commit(&[("svc/a.rs", Some("3\n"))], "master 2", START + 4 * DAY);
+        repo.commit(&[("svc/a.rs", Some("4\n"))], "master 3", START + 5 * DAY);
+
+        let revisions = |specs: &[&str]| AnalysisOptions::new().revisions(specs.iter().map(|s| (*s).to_owned()).collect());
+        assert_eq!(repo.num_commits(AnalysisOptions::new()), 4);
+        assert_eq!(repo.num_commits(revisions(&["feature"])), 3);
+        assert_eq!(repo.num_commits(revisions(&["feature..master"])), 3);
+        assert_eq!(repo.num_commits(revisions(&["master", "^feature"])), 3);
+        assert_eq!(repo.num_commits(revisions(&["master...feature"])), 5);
+        // as in git rev-list, exclusions apply to every tip, so the shared root is hidden too
+        assert_eq!(repo.num_commits(revisions(&["master~1..master", "feature"])), 3);
+        assert_eq!(repo.num_commits(revisions(&["--all"])), 6);
+    }
+
+    #[test]
+    fn test_since_and_until() {
+        let repo = TestRepo::new();
+        repo.commit(&[("svc/a.rs", Some("1\n"))], "root", START - DAY);
+        // unreadable, so the walk only succeeds if it stops before reaching it
+        repo.commit_with_author("A <a@example.com> 99999999999999999 +0000", "far future");
+        repo.commit(&[("svc/a.rs", Some("2\n"))], "second", START + 10 * DAY);
+        repo.commit(&[("svc/a.rs", Some("3\n"))], "third", START + 20 * DAY);
+
+        let at = |secs| Utc.timestamp_opt(secs, 0).unwrap();
+        assert_eq!(repo.num_commits(AnalysisOptions::new().since(at(START + 5 * DAY))), 2);
+        assert_eq!(repo.num_commits(AnalysisOptions::new().since(at(START + 10 * DAY))), 2);
+        assert_eq!(repo.num_commits(AnalysisOptions::new()
+            .since(at(START + 5 * DAY))
+            .until(at(START + 15 * DAY))), 1);
+        assert!(repo.run(AnalysisOptions::new().until(at(START + 15 * DAY))).is_err());
+    }
+
+    #[test]
+    fn test_lenient_skips_undecodable_paths() {
+        let repo = TestRepo::new();
+        repo.commit(&[("svc/a.rs", Some("1\n"))], "first", START);
+        repo.commit(&[("svc/a.rs", Some("2\n"))], "second", START + DAY);
+        // amend the second commit to also add a file whose name isn't UTF-8
+        let head = repo.repo.head().unwrap().peel_to_commit().unwrap();
+        let mut root = repo.repo.treebuilder(Some(&head.tree().unwrap())).unwrap();
+        root.insert(b"odd\xfe.rs".to_vec(), repo.repo.blob(b"3\n").unwrap(), 0o100_644).unwrap();
+        let tree = repo.repo.find_tree(root.write().unwrap()).unwrap();
+        let amended = head.amend(Some("HEAD"), None, None, None, None, Some(&tree)).unwrap();
+
+        assert!(repo.run(AnalysisOptions::new()).is_err());
+        let stats = repo.run(AnalysisOptions::new().lenient(true)).unwrap();
+        assert_eq!(stats.num_commits_to_master(), 2);
+        assert_eq!(stats.files()["svc/a.rs"].num_changes(), 2);
+        assert_eq!(stats.skipped().len(), 1);
+        assert_eq!(stats.skipped()[0].commit(), Some(amended.to_string().as_str()));
+        assert_eq!(stats.skipped()[0].path(), Some("odd\u{fffd}.rs"));
+    }
+
+    #[test]
+    fn test_parallel_matches_single_threaded() {
+        let repo = TestRepo::new();
+        let components = ["billing", "search", "web", "lib"];
+        for n in 0..40 {
+            let comp = components[n % components.len()];
+            let other = components[n * 7 % components.len()];
+            let lines = "x\n".repeat(n + 1);
+            repo.commit(&[(&format!("{}/f{}.rs", comp, n % 3), Some(&lines)),
+                          (&format!("{}/shared.py", other), Some(&lines))],
+                        &format!("change {} (#{})", n, n / 2), START + n as i64 * DAY / 3);
+        }
+
+        let opts = || AnalysisOptions::new().file_coupling(true).granularity(Granularity::Week);
+        let single = serde_json::to_value(repo.run(opts().jobs(1)).unwrap()).unwrap();
+        let parallel = serde_json::to_value(repo.run(opts().jobs(4)).unwrap()).unwrap();
+        assert_eq!(single["num_commits_to_master"], 40);
+        assert_eq!(single, parallel);
+    }
+
+    #[test]
+    fn test_cache_reuse_and_rewritten_history() {
+        let repo = TestRepo::new();
+        let cache = repo.path.join(".git").join("stats.json");
+        let cached = || AnalysisOptions::new().cache_file(&cache);
+        // shows whether a run reused the cache
+        let inflate_cache = || {
+            let mut saved: serde_json::Value = serde_json::from_str(&fs::read_to_string(&cache).unwrap()).unwrap();
+            saved["stats"]["num_commits_to_master"] = 100.into();
+            fs::write(&cache, saved.to_string()).unwrap();
+        };
+        repo.commit(&[("svc/a.rs", Some("1\n"))], "first", START);
+        let second = repo.commit(&[("svc/a.rs", Some("2\n"))], "second", START + DAY);
+        assert_eq!(repo.num_commits(cached()), 2);
+
+        // only the new commit is walked, the rest comes from the cache
+        inflate_cache();
+        repo.commit(&[("svc/a.rs", Some("3\n"))], "third", START + 2 * DAY);
+        assert_eq!(repo.num_commits(cached()), 101);
+
+        // a cache written with other options is ignored
+        assert_eq!(repo.num_commits(cached().find_copies(true)), 3);
+        assert_eq!(repo.num_commits(cached()), 3);
+
+        // as is one from another version
+        inflate_cache();
+        let mut saved: serde_json::Value = serde_json::from_str(&fs::read_to_string(&cache).unwrap()).unwrap();
+        saved["version"] = 0.into();
+        fs::write(&cache, saved.to_string()).unwrap();
+        assert_eq!(repo.num_commits(cached()), 3);
+
+        // the cached tip is no longer in the history once it is rewritten
+        inflate_cache();
+        let head = repo.repo.find_commit(second).unwrap();
+        repo.repo.reset(head.as_object(), git2::ResetType::Hard, None).unwrap();
+        repo.commit(&[("svc/b.rs", Some("1\n"))], "third, again", START + 3 * DAY);
+        let stats = repo.run(cached()).unwrap();
+        assert_eq!(stats.num_commits_to_master(), 3);
+        assert_eq!(stats.files()["svc/a.rs"].num_changes(), 2);
+        assert!(stats.files().contains_key("svc/b.rs"));
+        // the cache is renamed into place, leaving no temporary file behind
+        let names: Vec<String> = fs::read_dir(repo.path.join(".git")).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with("stats.json"))
+            .collect();
+        assert_eq!(names, vec!["stats.json"]);
+    }
+
+    #[test]
+    fn test_merge_strategies() {
+        let repo = TestRepo::new();
+        repo.commit(&[("lib/l.py", Some("1\n")), ("svc/a.rs", Some("1\n"))], "root", START);
+        repo.branch("feature");
+        repo.commit(&[("lib/l.py", Some("2\n"))], "on the branch", START + DAY);
+        repo.checkout("master");
+        repo.commit(&[("svc/a.rs", Some("2\n"))], "on master", START + 2 * DAY);
+        repo.merge("feature", "Merge pull request #7 from org/feature", START + 3 * DAY);
+
+        // commits counted, changes to lib/l.py, files modified by merges and
+        // the commits and files of the merged PR
+        let counts = |strategy| {
+            let stats = repo.run(AnalysisOptions::new().merge_strategy(strategy)).unwrap();
+            assert_eq!(stats.num_merges(), 1);
+            assert_eq!(stats.num_prs(), 1);
+            assert_eq!(stats.pr_sizes().num_prs(), 1);
+            let pr = &stats.prs()["#7"];
+            (stats.num_commits_to_master(), stats.files()["lib/l.py"].num_changes(),
+             stats.merge_changes().files_modified(), pr.num_commits(), pr.files_changed())
+        };
+        assert_eq!(counts(MergeStrategy::FirstParent), (3, 2, 0, 1, 1));
+        // the branch change is counted for its commit and again for the merge
+        assert_eq!(counts(MergeStrategy::DiffFirstParent), (4, 3, 0, 1, 1));
+        assert_eq!(counts(MergeStrategy::Separate), (3, 2, 1, 1, 1));
+        // skipped merges aren't diffed, so the PR has no files
+        assert_eq!(counts(MergeStrategy::Skip), (3, 2, 0, 1, 0));
+        assert_eq!(AnalysisOptions::default().merge_strategy, MergeStrategy::Separate);
+    }
+
+    #[test]
+    fn test_renames_copies_and_migrations() {
+        let repo = TestRepo::new();
+        let contents: String = (0..10).map(|n| format!("line {}\n", n)).collect();
+        repo.commit(&[("svc-a/x.py", Some(&contents))], "add", START);
+        repo.commit(&[("svc-a/x.py", None), ("svc-b/x.py", Some(&contents))], "move", START + DAY);
+        repo.commit(&[("svc-b/x.py", Some(&format!("{}line 10\n", contents))), ("svc-c/y.py", Some(&contents))],
+                    "copy", START + 2 * DAY);
+
+        let stats = repo.run(AnalysisOptions::new()).unwrap();
+        assert_eq!(stats.changes_by_component()["svc-b"].files_renamed(), 1);
+        assert_eq!(stats.component_migrations()["svc-a"]["svc-b"], 1);
+        assert_eq!(stats.changes_by_component()["svc-c"].files_added(), 1);
+        assert!(!stats.files()["svc-a/x.py"].exists());
+
+        let copies = repo.run(AnalysisOptions::new().find_copies(true)).unwrap();
+        assert_eq!(copies.changes_by_component()["svc-c"].files_copied(), 1);
+        assert_eq!(copies.changes_by_component()["svc-c"].files_added(), 0);
+
+        let no_renames = repo.run(AnalysisOptions::new().find_renames(false)).unwrap();
+        assert_eq!(no_renames.changes_by_component()["svc-a"].files_deleted(), 1);
+        assert_eq!(no_renames.changes_by_component()["svc-b"].files_added(), 1);
+        assert!(no_renames.component_migrations().is_empty());
+    }
+}
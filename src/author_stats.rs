@@ -0,0 +1,118 @@
+/*
+   Copyright 2019-2020 foamdino@gmail.com
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::collections::HashMap;
+use chrono::{DateTime, FixedOffset};
+use crate::CommitChanges;
+use crate::stats::merge_counts;
+
+/// What a single author contributed over the analysed history.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AuthorStats {
+    num_commits: u32,
+    num_prs: u32,
+    missing_prs: u32,
+    changes: CommitChanges,
+    components: HashMap<String, u32>,
+    langs: HashMap<String, u32>,
+    first_commit: DateTime<FixedOffset>,
+    last_commit: DateTime<FixedOffset>,
+}
+
+impl AuthorStats {
+
+    pub fn new(first_commit: DateTime<FixedOffset>) -> AuthorStats {
+        AuthorStats{
+            num_commits: 0,
+            num_prs: 0,
+            missing_prs: 0,
+            changes: CommitChanges::new(0, 0, 0),
+            components: HashMap::new(),
+            langs: HashMap::new(),
+            first_commit,
+            last_commit: first_commit,
+        }
+    }
+
+    pub fn num_commits(&self) -> u32 {
+        self.num_commits
+    }
+
+    pub fn num_prs(&self) -> u32 {
+        self.num_prs
+    }
+
+    pub fn missing_prs(&self) -> u32 {
+        self.missing_prs
+    }
+
+    pub fn changes(&self) -> CommitChanges {
+        self.changes
+    }
+
+    /// Number of commits touching each component.
+    pub fn components(&self) -> &HashMap<String, u32> {
+        &self.components
+    }
+
+    /// Number of commits touching each language.
+    pub fn langs(&self) -> &HashMap<String, u32> {
+        &self.langs
+    }
+
+    /// When the author's first commit was made, in the analysis timezone.
+    pub fn first_commit(&self) -> DateTime<FixedOffset> {
+        self.first_commit
+    }
+
+    pub fn last_commit(&self) -> DateTime<FixedOffset> {
+        self.last_commit
+    }
+
+    pub(crate) fn merge(&mut self, other: AuthorStats) {
+        self.num_commits += other.num_commits;
+        self.num_prs += other.num_prs;
+        self.missing_prs += other.missing_prs;
+        self.changes += other.changes;
+        merge_counts(&mut self.components, other.components);
+        merge_counts(&mut self.langs, other.langs);
+        self.first_commit = self.first_commit.min(other.first_commit);
+        self.last_commit = self.last_commit.max(other.last_commit);
+    }
+
+    pub(crate) fn record_commit(&mut self, when: DateTime<FixedOffset>, is_pr: bool) {
+        self.num_commits += 1;
+        if is_pr {
+            self.num_prs += 1;
+        } else {
+            self.missing_prs += 1;
+        }
+        self.first_commit = self.first_commit.min(when);
+        self.last_commit = self.last_commit.max(when);
+    }
+
+    pub(crate) fn record_changes(&mut self, changes: CommitChanges) {
+        self.changes += changes;
+    }
+
+    pub(crate) fn record_component(&mut self, comp_name: &str) {
+        *self.components.entry(comp_name.to_owned()).or_insert(0) += 1;
+    }
+
+    pub(crate) fn record_lang(&mut self, lang_name: &str) {
+        *self.langs.entry(lang_name.to_owned()).or_insert(0) += 1;
+    }
+}
@@ -15,20 +15,227 @@
 */
 extern crate serde_json;
 
+use std::collections::HashSet;
 use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
 use docopt::Docopt;
+use chrono::{DateTime, NaiveDate, Utc};
+use commit_analysis::{report, Analysis, AnalysisOptions, CodeOwners, Config, Format, Granularity, MergeStrategy, PathPattern, Timezone};
+
+const STDOUT: &str = "-";
+
+// accepts either a plain date, YYYY-MM-DD, which starts or ends in `timezone`,
+// or a full RFC 3339 timestamp
+fn parse_date(value: &str, timezone: Timezone, end_of_day: bool) -> DateTime<Utc> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return dt.with_timezone(&Utc);
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .unwrap_or_else(|_| fail(&format!("invalid date: {} (expected YYYY-MM-DD or RFC 3339)", value)));
+    let time = if end_of_day { date.and_hms_opt(23, 59, 59) } else { date.and_hms_opt(0, 0, 0) };
+    let instants = timezone.from_local_datetime(&time.unwrap());
+    let instant = if end_of_day { instants.latest() } else { instants.earliest() };
+    instant.unwrap_or_else(|| fail(&format!("invalid date: {} doesn't start or end in {}", value, timezone)))
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn write_output(output: &str, contents: &str) -> io::Result<()> {
+    if output == STDOUT {
+        let stdout = io::stdout();
+        let mut handle = stdout.lock();
+        handle.write_all(contents.as_bytes())?;
+        handle.write_all(b"\n")
+    } else {
+        fs::write(output, contents)
+    }
+}
+
+// one file per repository, named after the repository's directory
+fn output_paths(output_dir: &str, repo_paths: &[&str], extension: &str) -> Vec<PathBuf> {
+    let mut used: HashSet<String> = HashSet::new();
+    repo_paths.iter().map(|repo_path| {
+        let name = fs::canonicalize(repo_path).ok()
+            .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "repo".to_owned());
+        let mut unique = name.clone();
+        let mut n = 1;
+        while !used.insert(unique.clone()) {
+            n += 1;
+            unique = format!("{}-{}", name, n);
+        }
+        Path::new(output_dir).join(format!("{}.{}", unique, extension))
+    }).collect()
+}
 
 fn main() {
     const USAGE: &str = "
-Usage: commit-analysis <git_repo_path>
+Usage: commit-analysis [options] [--rev <spec>]... [--include <pattern>]... [--exclude <pattern>]... <git_repo_path>...
+
+Revisions are given as for git rev-list, e.g. v1.2..v1.3 or main and
+^release/1.x, with one --rev per revision. When none are given only HEAD
+is analysed.
+
+Paths are filtered with gitignore style patterns, e.g. --exclude vendor/
+or --exclude '*.lock'. Files are counted when they match an --include,
+or there are none, and match no --exclude.
+
+The report is written to stdout unless --output is given. When several
+repositories are analysed --output names a directory which gets one report
+per repository.
+
+Options:
+    --rev <spec>          Revision, range or exclusion to analyse.
+    --all                 Analyse the history reachable from every ref.
+    --include <pattern>   Only count files matching the pattern.
+    --exclude <pattern>   Don't count files matching the pattern.
+    --merges <how>        How merge commits are counted: first-parent,
+                          diff-first-parent, separate or skip. With
+                          diff-first-parent a change on a merged branch
+                          is counted twice, for the branch commit and
+                          again for the merge.
+                          [default: separate]
+    --since <date>        Only analyse commits authored on or after this date,
+                          read in --timezone unless it has an offset.
+    --until <date>        Only analyse commits authored on or before this date,
+                          read in --timezone unless it has an offset.
+    --timezone <tz>       Timezone commits are bucketed by month and weekday in:
+                          author, utc or an IANA name such as Europe/London.
+                          [default: author]
+    --granularity <g>     Period commits and changes are counted over time by:
+                          day, week, month, quarter or year.
+                          [default: month]
+    --aliases <file>      Extra author aliases, in .mailmap format.
+    --no-mailmap          Don't apply the repository's .mailmap to authors.
+    --no-line-stats       Only count changed files, not inserted and deleted lines.
+    --file-coupling       Count files changed together, not just components.
+    --no-renames          Count renamed files as deleted and added.
+    --copies              Detect copied files among the modified ones.
+    --similarity <pct>    How similar renamed or copied files must be, in percent.
+                          [default: 50]
+    --lenient             Skip and report commits and paths which can't be read
+                          instead of failing.
+    --format <fmt>        Output format: json, pretty-json, csv, markdown or table.
+                          [default: json]
+    --cache <path>        Reuse the stats from a previous run, only analysing new
+                          commits. A directory when analysing several repositories.
+    --config <file>       JSON config file, e.g. with component rules.
+    --components <how>    How files map to components: top, depth=<n> or
+                          codeowners. Overrides the config's component rules.
+    --code-age <rev>      Blame every file at this revision, e.g. HEAD, to report
+                          the age of the surviving code.
+    --top <n>             Length of ranked lists such as hotspots. [default: 20]
+    -v, --verbose         Write the time each stage of the analysis took to stderr.
+    -j, --jobs <n>        Number of threads diffing commits, 0 for one per CPU.
+                          [default: 0]
+    -o, --output <path>   File to write the report to, - for stdout.
+                          [default: -]
 ";
 
     let args = Docopt::new(USAGE)
         .and_then(|d| d.parse())
         .unwrap_or_else(|e| e.exit());
 
-    let output_file = "/tmp/commit-analysis.json";
-    let analysis = commit_analysis::walk_entire_history(args.get_str("<git_repo_path>"));
-    let json = serde_json::to_string(&analysis.unwrap());
-    fs::write(&output_file, json.unwrap()).unwrap_or_else(|_| panic!("couldn't write to file: {}", &output_file));
-}
\ No newline at end of file
+    let format: Format = args.get_str("--format").parse().unwrap_or_else(|e: String| fail(&e));
+    let merges: MergeStrategy = args.get_str("--merges").parse().unwrap_or_else(|e: String| fail(&e));
+    let timezone: Timezone = args.get_str("--timezone").parse().unwrap_or_else(|e: String| fail(&e));
+    let granularity: Granularity = args.get_str("--granularity").parse().unwrap_or_else(|e: String| fail(&e));
+    let mut revisions: Vec<String> = args.get_vec("--rev").iter().map(|r| (*r).to_owned()).collect();
+    if args.get_bool("--all") {
+        revisions.push("--all".to_owned());
+    }
+    let mut options = AnalysisOptions::new()
+        .revisions(revisions)
+        .merge_strategy(merges)
+        .timezone(timezone)
+        .granularity(granularity)
+        .use_mailmap(!args.get_bool("--no-mailmap"))
+        .line_stats(!args.get_bool("--no-line-stats"))
+        .file_coupling(args.get_bool("--file-coupling"))
+        .find_renames(!args.get_bool("--no-renames"))
+        .find_copies(args.get_bool("--copies"))
+        .similarity(args.get_str("--similarity").parse()
+            .unwrap_or_else(|_| fail(&format!("invalid similarity: {}", args.get_str("--similarity")))))
+        .lenient(args.get_bool("--lenient"))
+        .verbose(args.get_bool("--verbose"))
+        .top_n(args.get_str("--top").parse()
+            .unwrap_or_else(|_| fail(&format!("invalid list length: {}", args.get_str("--top")))))
+        .jobs(args.get_str("--jobs").parse()
+            .unwrap_or_else(|_| fail(&format!("invalid number of jobs: {}", args.get_str("--jobs")))));
+    if !args.get_str("--aliases").is_empty() {
+        options = options.alias_file(args.get_str("--aliases"));
+    }
+    if !args.get_str("--config").is_empty() {
+        options = Config::from_file(args.get_str("--config"))
+            .and_then(|config| config.apply(options))
+            .unwrap_or_else(|e| fail(&e.to_string()));
+    }
+    for pattern in args.get_vec("--include") {
+        options = options.include_path(PathPattern::new(pattern).unwrap_or_else(|e| fail(&e.to_string())));
+    }
+    for pattern in args.get_vec("--exclude") {
+        options = options.exclude_path(PathPattern::new(pattern).unwrap_or_else(|e| fail(&e.to_string())));
+    }
+    if !args.get_str("--code-age").is_empty() {
+        options = options.code_age(args.get_str("--code-age"));
+    }
+    if !args.get_str("--since").is_empty() {
+        options = options.since(parse_date(args.get_str("--since"), timezone, false));
+    }
+    if !args.get_str("--until").is_empty() {
+        options = options.until(parse_date(args.get_str("--until"), timezone, true));
+    }
+
+    let repo_paths = args.get_vec("<git_repo_path>");
+    let output = args.get_str("--output");
+    let outputs: Vec<PathBuf> = if repo_paths.len() == 1 {
+        vec![PathBuf::from(output)]
+    } else if output == STDOUT {
+        fail("--output must name a directory when analysing several repositories");
+    } else {
+        fs::create_dir_all(output)
+            .unwrap_or_else(|e| fail(&format!("couldn't create output directory {}: {}", output, e)));
+        output_paths(output, &repo_paths, format.extension())
+    };
+    let cache = args.get_str("--cache");
+    let caches: Vec<Option<PathBuf>> = if cache.is_empty() {
+        repo_paths.iter().map(|_| None).collect()
+    } else if repo_paths.len() == 1 {
+        vec![Some(PathBuf::from(cache))]
+    } else {
+        fs::create_dir_all(cache)
+            .unwrap_or_else(|e| fail(&format!("couldn't create cache directory {}: {}", cache, e)));
+        output_paths(cache, &repo_paths, "cache.json").into_iter().map(Some).collect()
+    };
+
+    for ((repo_path, output_file), cache_file) in repo_paths.iter().zip(outputs).zip(caches) {
+        let mut repo_options = options.clone();
+        let components = args.get_str("--components");
+        if components == "codeowners" {
+            let owners = CodeOwners::open(repo_path).unwrap_or_else(|e| fail(&e.to_string()));
+            repo_options = repo_options.component_resolver(owners);
+        } else if components == "top" {
+            repo_options = repo_options.component_depth(1);
+        } else if let Some(depth) = components.strip_prefix("depth=") {
+            let depth = depth.parse().unwrap_or_else(|_| fail(&format!("invalid component depth: {}", depth)));
+            repo_options = repo_options.component_depth(depth);
+        } else if !components.is_empty() {
+            fail(&format!("unknown component mapping: {} (expected top, depth=<n> or codeowners)", components));
+        }
+        if let Some(cache_file) = cache_file {
+            repo_options = repo_options.cache_file(cache_file);
+        }
+        let stats = Analysis::new(repo_path, repo_options).run()
+            .unwrap_or_else(|e| fail(&format!("couldn't analyse {}: {}", repo_path, e)));
+        let rendered = report::render(&stats, format)
+            .unwrap_or_else(|e| fail(&format!("couldn't render report for {}: {}", repo_path, e)));
+        let output_file = output_file.to_string_lossy();
+        write_output(&output_file, &rendered)
+            .unwrap_or_else(|e| fail(&format!("couldn't write to {}: {}", output_file, e)));
+    }
+}
@@ -0,0 +1,100 @@
+/*
+   Copyright 2019-2020 foamdino@gmail.com
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use git2::{Oid, Repository};
+use crate::{Error, Stats};
+
+// bumped whenever the shape of `Stats` changes, as fields missing from an older
+// cache would otherwise load as empty
+const CACHE_VERSION: u32 = 1;
+
+/// The stats of a previous run, with the commits that run started from.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct Cache {
+    version: u32,
+    fingerprint: String,
+    tips: Vec<String>,
+    stats: Stats,
+}
+
+impl Cache {
+
+    /// Loads the cache at `path`, if there is one written by this version with
+    /// the same options.
+    pub(crate) fn load(path: &Path, fingerprint: &str) -> Result<Option<Cache>, Error> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into())
+        };
+        // a cache which can't be parsed, or is from another version, is rebuilt from scratch
+        match serde_json::from_str::<Cache>(&contents) {
+            Ok(cache) if cache.version == CACHE_VERSION && cache.fingerprint == fingerprint => Ok(Some(cache)),
+            _ => Ok(None)
+        }
+    }
+
+    /// Writes `stats` to `path` and hands them back.
+    pub(crate) fn save(path: &Path, fingerprint: String, tips: &[Oid], stats: Stats) -> Result<Stats, Error> {
+        let cache = Cache{version: CACHE_VERSION, fingerprint, tips: tips.iter().map(|t| t.to_string()).collect(), stats};
+        let json = serde_json::to_string(&cache)
+            .map_err(|e| Error::Io(io::Error::other(e)))?;
+        // written next to the cache and renamed into place, so an interrupted
+        // or concurrent run never leaves a partly written cache behind
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(format!(".{}.tmp", process::id()));
+        let tmp = PathBuf::from(tmp);
+        if let Err(e) = fs::write(&tmp, json).and_then(|_| fs::rename(&tmp, path)) {
+            let _ = fs::remove_file(&tmp);
+            return Err(e.into());
+        }
+        Ok(cache.stats)
+    }
+
+    pub(crate) fn tips(&self) -> Result<Vec<Oid>, Error> {
+        Ok(self.tips.iter().map(|t| Oid::from_str(t)).collect::<Result<Vec<Oid>, git2::Error>>()?)
+    }
+
+    /// Whether every cached tip is still in the history of the current tips,
+    /// which stops being true when history is rewritten.
+    pub(crate) fn is_ancestor_of(&self, repo: &Repository, current: &[Oid]) -> Result<bool, Error> {
+        let cached = match self.tips() {
+            Ok(cached) => cached,
+            Err(_) => return Ok(false)
+        };
+        for tip in cached {
+            let mut reachable = false;
+            for head in current {
+                if *head == tip || repo.graph_descendant_of(*head, tip).unwrap_or(false) {
+                    reachable = true;
+                    break;
+                }
+            }
+            if !reachable {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    pub(crate) fn into_stats(self) -> Stats {
+        self.stats
+    }
+}
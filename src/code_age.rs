@@ -0,0 +1,120 @@
+/*
+   Copyright 2019-2020 foamdino@gmail.com
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::collections::HashMap;
+use crate::stats::merge_counts;
+
+/// Surviving lines, by who wrote them and the year they were written in.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct LineAges {
+    lines: u32,
+    by_author: HashMap<String, u32>,
+    by_year: HashMap<String, u32>,
+}
+
+impl LineAges {
+
+    pub fn lines(&self) -> u32 {
+        self.lines
+    }
+
+    pub fn by_author(&self) -> &HashMap<String, u32> {
+        &self.by_author
+    }
+
+    /// Lines by the year of the commit which last changed them.
+    pub fn by_year(&self) -> &HashMap<String, u32> {
+        &self.by_year
+    }
+
+    pub(crate) fn record_lines(&mut self, author: &str, year: &str, lines: u32) {
+        self.lines += lines;
+        *self.by_author.entry(author.to_owned()).or_insert(0) += lines;
+        *self.by_year.entry(year.to_owned()).or_insert(0) += lines;
+    }
+
+    pub(crate) fn merge(&mut self, other: LineAges) {
+        self.lines += other.lines;
+        merge_counts(&mut self.by_author, other.by_author);
+        merge_counts(&mut self.by_year, other.by_year);
+    }
+}
+
+/// The age of the code at a single revision, worked out by blaming every
+/// counted file, per component and per language.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct CodeAge {
+    revision: String,
+    by_component: HashMap<String, LineAges>,
+    by_lang: HashMap<String, LineAges>,
+}
+
+impl CodeAge {
+
+    pub fn new(revision: &str) -> CodeAge {
+        CodeAge{revision: revision.to_owned(), ..Default::default()}
+    }
+
+    /// The commit which was blamed.
+    pub fn revision(&self) -> &str {
+        &self.revision
+    }
+
+    pub fn by_component(&self) -> &HashMap<String, LineAges> {
+        &self.by_component
+    }
+
+    pub fn by_lang(&self) -> &HashMap<String, LineAges> {
+        &self.by_lang
+    }
+
+    pub(crate) fn record_lines(&mut self, comp_name: &str, lang_name: Option<&str>, author: &str, year: &str,
+                               lines: u32) {
+        self.by_component.entry(comp_name.to_owned()).or_default().record_lines(author, year, lines);
+        if let Some(lang_name) = lang_name {
+            self.by_lang.entry(lang_name.to_owned()).or_default().record_lines(author, year, lines);
+        }
+    }
+
+    pub(crate) fn merge(&mut self, other: CodeAge) {
+        for (comp_name, ages) in other.by_component {
+            self.by_component.entry(comp_name).or_default().merge(ages);
+        }
+        for (lang_name, ages) in other.by_lang {
+            self.by_lang.entry(lang_name).or_default().merge(ages);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_sums_lines_by_author_and_year() {
+        let mut a = CodeAge::new("HEAD");
+        a.record_lines("billing", Some("Rust"), "Jane <jane@example.com>", "2019", 10);
+        let mut b = CodeAge::new("HEAD");
+        b.record_lines("billing", None, "Jane <jane@example.com>", "2020", 5);
+        a.merge(b);
+
+        let billing = &a.by_component()["billing"];
+        assert_eq!(billing.lines(), 15);
+        assert_eq!(billing.by_author()["Jane <jane@example.com>"], 15);
+        assert_eq!(billing.by_year()["2019"], 10);
+        assert_eq!(a.by_lang()["Rust"].lines(), 10);
+    }
+}
@@ -16,17 +16,69 @@
 
 use std::ops::{Add, AddAssign};
 
-#[derive(Debug, Deserialize, Serialize, Copy, Clone)]
+#[derive(Debug, Default, Deserialize, Serialize, Copy, Clone)]
 pub struct CommitChanges {
     files_added: u32,
     files_deleted: u32,
     files_modified: u32,
+    #[serde(default)]
+    files_renamed: u32,
+    #[serde(default)]
+    files_copied: u32,
+    #[serde(default)]
+    lines_inserted: u32,
+    #[serde(default)]
+    lines_deleted: u32,
 }
 
 impl CommitChanges {
 
     pub const fn new(fa: u32, fd: u32, fm: u32) -> CommitChanges {
-        CommitChanges{files_added: fa, files_deleted: fd, files_modified: fm}
+        CommitChanges{files_added: fa, files_deleted: fd, files_modified: fm, files_renamed: 0, files_copied: 0,
+                      lines_inserted: 0, lines_deleted: 0}
+    }
+
+    /// The same file changes with the given renamed and copied files.
+    pub const fn with_moves(self, fr: u32, fc: u32) -> CommitChanges {
+        CommitChanges{files_renamed: fr, files_copied: fc, ..self}
+    }
+
+    /// The same file changes with the given line churn.
+    pub const fn with_lines(self, li: u32, ld: u32) -> CommitChanges {
+        CommitChanges{lines_inserted: li, lines_deleted: ld, ..self}
+    }
+
+    pub fn files_added(&self) -> u32 {
+        self.files_added
+    }
+
+    pub fn files_deleted(&self) -> u32 {
+        self.files_deleted
+    }
+
+    pub fn files_modified(&self) -> u32 {
+        self.files_modified
+    }
+
+    pub fn files_renamed(&self) -> u32 {
+        self.files_renamed
+    }
+
+    pub fn files_copied(&self) -> u32 {
+        self.files_copied
+    }
+
+    pub fn lines_inserted(&self) -> u32 {
+        self.lines_inserted
+    }
+
+    pub fn lines_deleted(&self) -> u32 {
+        self.lines_deleted
+    }
+
+    /// Lines inserted plus lines deleted.
+    pub fn churn(&self) -> u32 {
+        self.lines_inserted + self.lines_deleted
     }
 }
 
@@ -36,8 +88,12 @@ impl Add for CommitChanges {
     fn add(self, other: Self) -> Self {
         Self {
             files_added: self.files_added + other.files_added,
-            files_deleted: self.files_added + other.files_deleted,
-            files_modified: self.files_modified + other.files_modified
+            files_deleted: self.files_deleted + other.files_deleted,
+            files_modified: self.files_modified + other.files_modified,
+            files_renamed: self.files_renamed + other.files_renamed,
+            files_copied: self.files_copied + other.files_copied,
+            lines_inserted: self.lines_inserted + other.lines_inserted,
+            lines_deleted: self.lines_deleted + other.lines_deleted
         }
     }
 }
@@ -46,8 +102,12 @@ impl AddAssign for CommitChanges {
     fn add_assign(&mut self, other: CommitChanges) {
         *self = CommitChanges {
             files_added: self.files_added + other.files_added,
-            files_deleted: self.files_added + other.files_deleted,
-            files_modified: self.files_modified + other.files_modified
+            files_deleted: self.files_deleted + other.files_deleted,
+            files_modified: self.files_modified + other.files_modified,
+            files_renamed: self.files_renamed + other.files_renamed,
+            files_copied: self.files_copied + other.files_copied,
+            lines_inserted: self.lines_inserted + other.lines_inserted,
+            lines_deleted: self.lines_deleted + other.lines_deleted
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,209 @@
+/*
+   Copyright 2019-2020 foamdino@gmail.com
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::fmt;
+use git2::Repository;
+use crate::{extract_component_name_from_filename, Error, PathPattern};
+
+const PATH_SPLIT: &str = "/";
+const CODEOWNERS_LOCATIONS: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// Decides which component a changed file belongs to.
+pub trait ComponentResolver: fmt::Debug + Send + Sync {
+    /// The component owning `path`, or `None` when it doesn't belong to one.
+    fn component(&self, path: &str) -> Option<String>;
+}
+
+/// The top level directory is the component, e.g. `component-a/src/Thing.java`
+/// belongs to `component-a`. Files in the root of the repository belong to none.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TopLevelDir;
+
+impl ComponentResolver for TopLevelDir {
+    fn component(&self, path: &str) -> Option<String> {
+        if !path.contains(PATH_SPLIT) {
+            return None;
+        }
+        extract_component_name_from_filename(path)
+    }
+}
+
+/// The first `depth` directories are the component, e.g. with a depth of 2
+/// `services/billing/src/main.rs` belongs to `services/billing`.
+#[derive(Debug, Clone, Copy)]
+pub struct DirectoryDepth {
+    depth: usize,
+}
+
+impl DirectoryDepth {
+
+    pub fn new(depth: usize) -> DirectoryDepth {
+        DirectoryDepth{depth: depth.max(1)}
+    }
+}
+
+impl ComponentResolver for DirectoryDepth {
+    fn component(&self, path: &str) -> Option<String> {
+        let name_parts = path.split(PATH_SPLIT).collect::<Vec<&str>>();
+        // the last part is the file itself, never a directory
+        let depth = self.depth.min(name_parts.len() - 1);
+        if depth == 0 {
+            return None;
+        }
+        Some(name_parts[..depth].join(PATH_SPLIT))
+    }
+}
+
+/// A pattern mapping paths to a component, see `ComponentRules`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ComponentRule {
+    pub pattern: String,
+    #[serde(default)]
+    pub component: Option<String>,
+}
+
+/// Glob rules, the first matching rule decides the component.
+///
+/// A rule with a name puts everything it matches in that component. A rule
+/// without one names the component after the directory it matched, so
+/// `services/*` puts `services/billing/src/main.rs` in `services/billing`.
+#[derive(Debug, Clone)]
+pub struct ComponentRules {
+    rules: Vec<(PathPattern, Option<String>)>,
+}
+
+impl ComponentRules {
+
+    pub fn new(rules: &[ComponentRule]) -> Result<ComponentRules, Error> {
+        let rules = rules.iter()
+            .map(|r| Ok((PathPattern::new(&r.pattern)?, r.component.clone())))
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(ComponentRules{rules})
+    }
+}
+
+impl ComponentResolver for ComponentRules {
+    fn component(&self, path: &str) -> Option<String> {
+        let (pattern, name) = self.rules.iter().find(|(p, _)| p.matches(path))?;
+        if let Some(name) = name {
+            return Some(name.clone());
+        }
+        // the shortest leading part of the path the pattern matches
+        let mut end = 0;
+        for part in path.split(PATH_SPLIT) {
+            end += part.len();
+            if pattern.matches(&path[..end]) {
+                return Some(path[..end].to_owned());
+            }
+            end += PATH_SPLIT.len();
+        }
+        Some(path.to_owned())
+    }
+}
+
+/// Components are the owners given by the repository's CODEOWNERS file, where
+/// the last matching line wins.
+#[derive(Debug, Clone)]
+pub struct CodeOwners {
+    rules: Vec<(PathPattern, Vec<String>)>,
+}
+
+impl CodeOwners {
+
+    pub fn parse(contents: &str) -> Result<CodeOwners, Error> {
+        let mut rules = vec![];
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let pattern = match parts.next() {
+                Some(p) => PathPattern::new(p)?,
+                None => continue
+            };
+            let owners = parts.take_while(|o| !o.starts_with('#')).map(|o| o.to_owned()).collect();
+            rules.push((pattern, owners));
+        }
+        Ok(CodeOwners{rules})
+    }
+
+    /// Reads the CODEOWNERS file from HEAD of the repository at `repo_path`,
+    /// looking in the same places GitHub does.
+    pub fn open(repo_path: &str) -> Result<CodeOwners, Error> {
+        let repo = Repository::open(repo_path)?;
+        let tree = repo.head()?.peel_to_tree()?;
+        for location in CODEOWNERS_LOCATIONS.iter() {
+            if let Ok(entry) = tree.get_path(std::path::Path::new(location)) {
+                let blob = entry.to_object(&repo)?.peel_to_blob()?;
+                return CodeOwners::parse(&String::from_utf8_lossy(blob.content()));
+            }
+        }
+        Err(Error::Config(format!("no CODEOWNERS file in {}", repo_path)))
+    }
+}
+
+impl ComponentResolver for CodeOwners {
+    fn component(&self, path: &str) -> Option<String> {
+        let (_, owners) = self.rules.iter().rev().find(|(p, _)| p.matches(path))?;
+        if owners.is_empty() {
+            None
+        } else {
+            Some(owners.join(" "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_directory_depth() {
+        let resolver = DirectoryDepth::new(2);
+        assert_eq!(resolver.component("services/billing/src/main.rs").unwrap(), "services/billing");
+        assert_eq!(resolver.component("services/README.md").unwrap(), "services");
+        assert!(resolver.component("README.md").is_none());
+    }
+
+    #[test]
+    fn test_component_rules() {
+        let rules = ComponentRules::new(&[
+            ComponentRule{pattern: "services/*".to_owned(), component: None},
+            ComponentRule{pattern: "libs/*".to_owned(), component: None},
+            ComponentRule{pattern: "*.md".to_owned(), component: Some("docs".to_owned())},
+        ]).unwrap();
+        assert_eq!(rules.component("services/billing/src/main.rs").unwrap(), "services/billing");
+        assert_eq!(rules.component("libs/core/lib.rs").unwrap(), "libs/core");
+        assert_eq!(rules.component("guide/intro.md").unwrap(), "docs");
+        assert!(rules.component("tools/build.sh").is_none());
+    }
+
+    #[test]
+    fn test_codeowners_last_match_wins() {
+        let owners = CodeOwners::parse("
+# default owners
+*           @org/everyone
+/services/  @org/backend
+/services/billing/ @org/payments @alice
+/services/legacy/
+").unwrap();
+        assert_eq!(owners.component("README.md").unwrap(), "@org/everyone");
+        assert_eq!(owners.component("services/auth/main.go").unwrap(), "@org/backend");
+        assert_eq!(owners.component("services/billing/main.go").unwrap(), "@org/payments @alice");
+        assert!(owners.component("services/legacy/main.go").is_none());
+    }
+}
@@ -0,0 +1,126 @@
+/*
+   Copyright 2019-2020 foamdino@gmail.com
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use crate::{AnalysisOptions, ComponentRule, ComponentRules, Error, LanguageRegistry, PathPattern, ReferenceExtractor, ReferenceRule};
+
+/// Analysis settings read from a JSON config file, e.g.
+///
+/// ```json
+/// {
+///   "exclude_paths": ["vendor/", "**/generated/**", "*.lock"],
+///   "component_rules": [
+///     {"pattern": "services/*"},
+///     {"pattern": "docs/", "component": "documentation"}
+///   ],
+///   "reference_rules": [
+///     {"kind": "pr", "pattern": "\\((#\\d+)\\)"},
+///     {"kind": "issue", "pattern": "\\b(OPS-\\d+)\\b"}
+///   ],
+///   "jira_projects": ["PAY", "OPS"],
+///   "languages": {
+///     "only": ["Java", "Kotlin", "Jinja"],
+///     "extensions": {"tpl": "Jinja"}
+///   }
+/// }
+/// ```
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    /// Only files matching one of these gitignore style patterns are counted.
+    pub include_paths: Vec<String>,
+    /// Files matching any of these gitignore style patterns are not counted.
+    pub exclude_paths: Vec<String>,
+    /// Glob rules mapping paths to components, see `ComponentRules`.
+    pub component_rules: Vec<ComponentRule>,
+    /// Regexes finding PR and issue IDs in commit messages, replacing the
+    /// built in ones, see `ReferenceExtractor`.
+    pub reference_rules: Vec<ReferenceRule>,
+    /// Jira project keys whose issue keys, such as `PAY-12`, are counted as issues.
+    pub jira_projects: Vec<String>,
+    pub languages: LanguageConfig,
+}
+
+/// Additions to the built in `LanguageRegistry` and the languages to count.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LanguageConfig {
+    /// Only these languages are counted, instead of every detected one.
+    pub only: Option<Vec<String>>,
+    /// Extra extensions, without the dot, mapped to a language name.
+    pub extensions: BTreeMap<String, String>,
+    /// Extra exact file names mapped to a language name.
+    pub filenames: BTreeMap<String, String>,
+    /// Extra shebang interpreters mapped to a language name.
+    pub interpreters: BTreeMap<String, String>,
+}
+
+impl LanguageConfig {
+
+    /// The built in registry extended with the configured mappings.
+    pub fn registry(&self) -> LanguageRegistry {
+        let mut registry = LanguageRegistry::new();
+        for (ext, language) in &self.extensions {
+            registry.add_extension(ext, language);
+        }
+        for (filename, language) in &self.filenames {
+            registry.add_filename(filename, language);
+        }
+        for (interpreter, language) in &self.interpreters {
+            registry.add_interpreter(interpreter, language);
+        }
+        registry
+    }
+}
+
+impl Config {
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Config, Error> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+        serde_json::from_str(&contents)
+            .map_err(|e| Error::Config(format!("couldn't parse {}: {}", path.display(), e)))
+    }
+
+    /// Applies the settings given in the config on top of `options`.
+    pub fn apply(&self, mut options: AnalysisOptions) -> Result<AnalysisOptions, Error> {
+        for pattern in &self.include_paths {
+            options = options.include_path(PathPattern::new(pattern)?);
+        }
+        for pattern in &self.exclude_paths {
+            options = options.exclude_path(PathPattern::new(pattern)?);
+        }
+        if !self.component_rules.is_empty() {
+            options = options.component_resolver(ComponentRules::new(&self.component_rules)?);
+        }
+        if !self.reference_rules.is_empty() || !self.jira_projects.is_empty() {
+            let mut references = if self.reference_rules.is_empty() {
+                ReferenceExtractor::new()
+            } else {
+                ReferenceExtractor::with_rules(&self.reference_rules)?
+            };
+            references.add_jira_projects(&self.jira_projects)?;
+            options = options.references(references);
+        }
+        options = options.language_registry(self.languages.registry());
+        if let Some(only) = &self.languages.only {
+            options = options.languages(only.clone());
+        }
+        Ok(options)
+    }
+}
@@ -0,0 +1,127 @@
+/*
+   Copyright 2019-2020 foamdino@gmail.com
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::collections::HashMap;
+
+// pairs changed together less often than this are too noisy to rank
+const MIN_CO_CHANGES: u32 = 2;
+
+/// Co-change counts, keyed by the lesser name of each pair and then the greater.
+pub type CouplingMatrix = HashMap<String, HashMap<String, u32>>;
+
+/// Counts every pair of `names` as changed together once.
+pub(crate) fn record_co_changes<'a, I>(matrix: &mut CouplingMatrix, names: I)
+    where I: IntoIterator<Item = &'a String> {
+    let mut names: Vec<&String> = names.into_iter().collect();
+    names.sort();
+    names.dedup();
+    for (i, a) in names.iter().enumerate() {
+        for b in &names[i + 1..] {
+            *matrix.entry((*a).clone()).or_default().entry((*b).clone()).or_insert(0) += 1;
+        }
+    }
+}
+
+/// Two things which change in the same commits.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Coupling {
+    a: String,
+    b: String,
+    co_changes: u32,
+    support: f64,
+    confidence: f64,
+}
+
+impl Coupling {
+
+    /// The `top_n` most strongly coupled pairs, by confidence and then by
+    /// co-changes. `changes` holds the number of commits changing each name,
+    /// pairs with a name missing from it are left out.
+    pub(crate) fn rank(matrix: &CouplingMatrix, changes: &HashMap<String, u32>, num_commits: u32,
+                       top_n: usize) -> Vec<Coupling> {
+        let mut pairs = vec![];
+        for (a, partners) in matrix {
+            for (b, co_changes) in partners {
+                let (a_changes, b_changes) = match (changes.get(a), changes.get(b)) {
+                    (Some(a_changes), Some(b_changes)) if *co_changes >= MIN_CO_CHANGES => (*a_changes, *b_changes),
+                    _ => continue
+                };
+                // of the two rules a => b and b => a, the one from the less changed side
+                let fewest = a_changes.min(b_changes).max(*co_changes);
+                pairs.push(Coupling{
+                    a: a.clone(),
+                    b: b.clone(),
+                    co_changes: *co_changes,
+                    support: f64::from(*co_changes) / f64::from(num_commits.max(1)),
+                    confidence: f64::from(*co_changes) / f64::from(fewest),
+                });
+            }
+        }
+        pairs.sort_by(|x, y| y.confidence.total_cmp(&x.confidence)
+            .then_with(|| y.co_changes.cmp(&x.co_changes))
+            .then_with(|| (&x.a, &x.b).cmp(&(&y.a, &y.b))));
+        pairs.truncate(top_n);
+        pairs
+    }
+
+    pub fn a(&self) -> &str {
+        &self.a
+    }
+
+    pub fn b(&self) -> &str {
+        &self.b
+    }
+
+    /// Number of commits changing both.
+    pub fn co_changes(&self) -> u32 {
+        self.co_changes
+    }
+
+    /// Share of all commits changing both.
+    pub fn support(&self) -> f64 {
+        self.support
+    }
+
+    /// Share of the commits changing the less frequently changed of the two
+    /// which also change the other.
+    pub fn confidence(&self) -> f64 {
+        self.confidence
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_coupled_pairs() {
+        let names = |n: &[&str]| n.iter().map(|s| (*s).to_owned()).collect::<Vec<String>>();
+        let mut matrix = CouplingMatrix::new();
+        record_co_changes(&mut matrix, &names(&["billing", "auth"]));
+        record_co_changes(&mut matrix, &names(&["auth", "billing", "web"]));
+        record_co_changes(&mut matrix, &names(&["web", "auth"]));
+        assert_eq!(matrix["auth"]["billing"], 2);
+
+        let changes: HashMap<String, u32> = [("auth", 4), ("billing", 2), ("web", 3)].iter()
+            .map(|(n, c)| ((*n).to_owned(), *c)).collect();
+        let ranked = Coupling::rank(&matrix, &changes, 5, 10);
+        assert_eq!(ranked.len(), 2);
+        assert_eq!((ranked[0].a(), ranked[0].b()), ("auth", "billing"));
+        assert_eq!(ranked[0].confidence(), 1.0);
+        assert_eq!(ranked[0].support(), 0.4);
+        assert_eq!((ranked[1].a(), ranked[1].b()), ("auth", "web"));
+    }
+}
@@ -0,0 +1,95 @@
+/*
+   Copyright 2019-2020 foamdino@gmail.com
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::error;
+use std::fmt;
+use std::io;
+
+/// Everything which can go wrong while analysing a repository.
+#[derive(Debug)]
+pub enum Error {
+    /// libgit2 failed to read the repository.
+    Git(git2::Error),
+    /// A file outside the repository, such as an alias file, couldn't be read.
+    Io(io::Error),
+    /// A path or message in the repository isn't valid UTF-8.
+    Encoding(String),
+    /// The analysis was set up with options which can't be used.
+    Config(String),
+    /// A commit records a time which can't be represented.
+    Time(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Git(e) => write!(f, "git error: {}", e),
+            Error::Io(e) => write!(f, "io error: {}", e),
+            Error::Encoding(msg) => write!(f, "encoding error: {}", msg),
+            Error::Config(msg) => write!(f, "config error: {}", msg),
+            Error::Time(msg) => write!(f, "time error: {}", msg),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Git(e) => Some(e),
+            Error::Io(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<git2::Error> for Error {
+    fn from(e: git2::Error) -> Self {
+        Error::Git(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// A commit, or a single path within one, left out of a lenient analysis.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Skipped {
+    commit: Option<String>,
+    path: Option<String>,
+    reason: String,
+}
+
+impl Skipped {
+
+    pub fn new(commit: Option<String>, path: Option<String>, reason: String) -> Skipped {
+        Skipped{commit, path, reason}
+    }
+
+    pub fn commit(&self) -> Option<&str> {
+        self.commit.as_deref()
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
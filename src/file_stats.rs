@@ -0,0 +1,160 @@
+/*
+   Copyright 2019-2020 foamdino@gmail.com
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::collections::{BTreeMap, HashMap};
+use chrono::{DateTime, FixedOffset};
+use crate::CommitChanges;
+
+/// How a single file, by its current path, changed over the analysed history.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct FileStats {
+    num_changes: u32,
+    authors: BTreeMap<String, u32>,
+    changes: CommitChanges,
+    last_modified: DateTime<FixedOffset>,
+    #[serde(default)]
+    removed: Option<DateTime<FixedOffset>>,
+}
+
+impl FileStats {
+
+    pub fn new(last_modified: DateTime<FixedOffset>) -> FileStats {
+        FileStats{num_changes: 0, authors: BTreeMap::new(), changes: CommitChanges::new(0, 0, 0), last_modified,
+                  removed: None}
+    }
+
+    /// Number of commits changing the file.
+    pub fn num_changes(&self) -> u32 {
+        self.num_changes
+    }
+
+    /// Number of commits changing the file by each author.
+    pub fn authors(&self) -> &BTreeMap<String, u32> {
+        &self.authors
+    }
+
+    pub fn changes(&self) -> CommitChanges {
+        self.changes
+    }
+
+    /// When the file was last changed, in the analysis timezone.
+    pub fn last_modified(&self) -> DateTime<FixedOffset> {
+        self.last_modified
+    }
+
+    /// Whether the file still exists, i.e. it wasn't deleted or renamed away
+    /// after it was last changed.
+    pub fn exists(&self) -> bool {
+        self.removed.is_none_or(|removed| removed < self.last_modified)
+    }
+
+    /// The author who changed the file most often, the earliest name first on a tie.
+    pub fn main_author(&self) -> Option<&str> {
+        self.authors.iter()
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+            .map(|(author, _)| author.as_str())
+    }
+
+    pub(crate) fn merge(&mut self, other: FileStats) {
+        self.num_changes += other.num_changes;
+        for (author, count) in other.authors {
+            *self.authors.entry(author).or_insert(0) += count;
+        }
+        self.changes += other.changes;
+        self.last_modified = self.last_modified.max(other.last_modified);
+        self.removed = self.removed.max(other.removed);
+    }
+
+    pub(crate) fn record_change(&mut self, author: &str, when: DateTime<FixedOffset>, changes: CommitChanges) {
+        self.num_changes += 1;
+        *self.authors.entry(author.to_owned()).or_insert(0) += 1;
+        self.changes += changes;
+        self.last_modified = self.last_modified.max(when);
+    }
+
+    pub(crate) fn record_removal(&mut self, when: DateTime<FixedOffset>) {
+        self.removed = self.removed.max(Some(when));
+    }
+}
+
+/// A file ranked by how often and how heavily it changes.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Hotspot {
+    path: String,
+    num_changes: u32,
+    churn: u32,
+    score: u64,
+}
+
+impl Hotspot {
+
+    /// The `top_n` existing files with the highest change count times churn, highest first.
+    pub(crate) fn rank(files: &HashMap<String, FileStats>, top_n: usize) -> Vec<Hotspot> {
+        let mut hotspots: Vec<Hotspot> = files.iter().filter(|(_, file)| file.exists()).map(|(path, file)| {
+            let churn = file.changes().churn();
+            // without line stats every change weighs the same
+            let score = u64::from(file.num_changes()) * u64::from(churn.max(1));
+            Hotspot{path: path.clone(), num_changes: file.num_changes(), churn, score}
+        }).collect();
+        hotspots.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+        hotspots.truncate(top_n);
+        hotspots
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn num_changes(&self) -> u32 {
+        self.num_changes
+    }
+
+    pub fn churn(&self) -> u32 {
+        self.churn
+    }
+
+    /// Number of changes multiplied by lines churned.
+    pub fn score(&self) -> u64 {
+        self.score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_rank_by_frequency_times_churn() {
+        let when = Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap().fixed_offset();
+        let mut files = HashMap::new();
+        for (path, changes, lines) in [("a.rs", 10, 1), ("b.rs", 2, 40), ("c.rs", 1, 5)] {
+            let mut file = FileStats::new(when);
+            for _ in 0..changes {
+                file.record_change("Jane <jane@example.com>", when, CommitChanges::new(0, 0, 1).with_lines(lines, 0));
+            }
+            files.insert(path.to_owned(), file);
+        }
+        let mut deleted = FileStats::new(when);
+        deleted.record_change("Jane <jane@example.com>", when, CommitChanges::new(1, 0, 0).with_lines(1000, 0));
+        deleted.record_removal(Utc.with_ymd_and_hms(2020, 2, 1, 0, 0, 0).unwrap().fixed_offset());
+        files.insert("gone.rs".to_owned(), deleted);
+
+        let hotspots = Hotspot::rank(&files, 2);
+        assert_eq!(hotspots.iter().map(|h| h.path()).collect::<Vec<_>>(), vec!["b.rs", "a.rs"]);
+        assert_eq!(hotspots[0].score(), 160);
+    }
+}
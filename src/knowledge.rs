@@ -0,0 +1,133 @@
+/*
+   Copyright 2019-2020 foamdino@gmail.com
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::collections::HashMap;
+
+/// One author's part in the changes to a component.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct AuthorShare {
+    author: String,
+    changes: u32,
+    share: f64,
+}
+
+impl AuthorShare {
+
+    pub fn author(&self) -> &str {
+        &self.author
+    }
+
+    /// Number of file changes to the component.
+    pub fn changes(&self) -> u32 {
+        self.changes
+    }
+
+    /// Share of all file changes to the component, between 0 and 1.
+    pub fn share(&self) -> f64 {
+        self.share
+    }
+}
+
+/// How the knowledge of a component is spread over its authors.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Knowledge {
+    authors: Vec<AuthorShare>,
+    authors_50: u32,
+    authors_80: u32,
+    bus_factor: u32,
+}
+
+impl Knowledge {
+
+    /// `changes` holds the file changes to the component by each author and
+    /// `main_authors` the author who changed each of its files the most.
+    pub(crate) fn new<'a, I>(changes: &HashMap<String, u32>, main_authors: I) -> Knowledge
+        where I: IntoIterator<Item = &'a str> {
+        let total: u32 = changes.values().sum();
+        let mut authors: Vec<AuthorShare> = changes.iter().map(|(author, c)| AuthorShare{
+            author: author.clone(),
+            changes: *c,
+            share: f64::from(*c) / f64::from(total.max(1)),
+        }).collect();
+        authors.sort_by(|a, b| b.changes.cmp(&a.changes).then_with(|| a.author.cmp(&b.author)));
+        let counts: Vec<u32> = authors.iter().map(|a| a.changes).collect();
+
+        let mut files_owned: HashMap<&str, u32> = HashMap::new();
+        for author in main_authors {
+            *files_owned.entry(author).or_insert(0) += 1;
+        }
+        let mut owned: Vec<u32> = files_owned.into_values().collect();
+        owned.sort_unstable_by(|a, b| b.cmp(a));
+
+        Knowledge{
+            authors,
+            authors_50: authors_covering(&counts, 50),
+            authors_80: authors_covering(&counts, 80),
+            bus_factor: authors_covering(&owned, 50),
+        }
+    }
+
+    /// Every author with their share of the changes, largest first.
+    pub fn authors(&self) -> &[AuthorShare] {
+        &self.authors
+    }
+
+    /// Fewest authors who made at least half of the changes.
+    pub fn authors_50(&self) -> u32 {
+        self.authors_50
+    }
+
+    /// Fewest authors who made at least 80% of the changes.
+    pub fn authors_80(&self) -> u32 {
+        self.authors_80
+    }
+
+    /// Fewest authors who'd have to leave before at least half of the
+    /// component's current files had lost their main author.
+    pub fn bus_factor(&self) -> u32 {
+        self.bus_factor
+    }
+}
+
+// how many of the largest counts, sorted largest first, add up to `percent` of them all
+fn authors_covering(sorted: &[u32], percent: u64) -> u32 {
+    let total: u64 = sorted.iter().map(|c| u64::from(*c)).sum();
+    let mut covered = 0;
+    for (n, count) in sorted.iter().enumerate() {
+        covered += u64::from(*count);
+        if covered * 100 >= total * percent {
+            return n as u32 + 1;
+        }
+    }
+    sorted.len() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_knowledge_concentration() {
+        let changes: HashMap<String, u32> = [("alice", 60), ("bob", 25), ("carol", 15)].iter()
+            .map(|(a, c)| ((*a).to_owned(), *c)).collect();
+        let knowledge = Knowledge::new(&changes, vec!["alice", "alice", "bob", "carol"]);
+        assert_eq!(knowledge.authors()[0].author(), "alice");
+        assert_eq!(knowledge.authors()[0].share(), 0.6);
+        assert_eq!(knowledge.authors_50(), 1);
+        assert_eq!(knowledge.authors_80(), 2);
+        assert_eq!(knowledge.bus_factor(), 1);
+    }
+}
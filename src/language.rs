@@ -0,0 +1,212 @@
+/*
+   Copyright 2019-2020 foamdino@gmail.com
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::collections::BTreeMap;
+
+const PATH_SPLIT: char = '/';
+const EXT_SPLIT: char = '.';
+
+// (language, extensions, exact file names, shebang interpreters)
+type LanguageDef = (&'static str, &'static [&'static str], &'static [&'static str], &'static [&'static str]);
+
+const BUILTIN_LANGUAGES: [LanguageDef; 48] = [
+    ("C", &["c", "h"], &[], &[]),
+    ("C#", &["cs", "csx"], &[], &[]),
+    ("C++", &["cpp", "cc", "cxx", "hpp", "hh", "hxx"], &[], &[]),
+    ("CSS", &["css"], &[], &[]),
+    ("Clojure", &["clj", "cljs", "cljc", "edn"], &[], &[]),
+    ("CMake", &["cmake"], &["CMakeLists.txt"], &[]),
+    ("Dart", &["dart"], &[], &[]),
+    ("Dockerfile", &["dockerfile"], &["Dockerfile", "Containerfile"], &[]),
+    ("Elixir", &["ex", "exs"], &[], &["elixir"]),
+    ("Erlang", &["erl", "hrl"], &[], &["escript"]),
+    ("Git Config", &[], &[".gitmodules", ".gitattributes", ".gitconfig"], &[]),
+    ("Go", &["go"], &[], &[]),
+    ("Gradle", &["gradle"], &[], &[]),
+    ("Groovy", &["groovy", "gvy"], &["Jenkinsfile"], &["groovy"]),
+    ("HCL", &["hcl", "tf", "tfvars"], &[], &[]),
+    ("HTML", &["html", "htm", "xhtml"], &[], &[]),
+    ("Haskell", &["hs", "lhs"], &[], &["runhaskell"]),
+    ("Ignore List", &["gitignore", "dockerignore"], &[".gitignore", ".dockerignore", ".npmignore"], &[]),
+    ("JSON", &["json"], &[".babelrc", ".eslintrc"], &[]),
+    ("Java", &["java"], &[], &[]),
+    ("Java Properties", &["properties"], &[], &[]),
+    ("JavaScript", &["js", "mjs", "cjs", "jsx"], &[], &["node"]),
+    ("Jinja", &["j2", "jinja", "jinja2"], &[], &[]),
+    ("Kotlin", &["kt", "kts"], &[], &[]),
+    ("Less", &["less"], &[], &[]),
+    ("Lua", &["lua"], &[], &["lua"]),
+    ("Makefile", &["mk", "mak"], &["Makefile", "GNUmakefile", "makefile"], &["make"]),
+    ("Markdown", &["md", "markdown"], &[], &[]),
+    ("Objective-C", &["m"], &[], &[]),
+    ("PHP", &["php"], &[], &["php"]),
+    ("Perl", &["pl", "pm"], &[], &["perl"]),
+    ("Protocol Buffer", &["proto"], &[], &[]),
+    ("Python", &["py", "pyi", "pyw"], &["SConstruct", "SConscript"], &["python", "python2", "python3"]),
+    ("R", &["r"], &[], &["Rscript"]),
+    ("Ruby", &["rb", "rake", "gemspec"], &["Gemfile", "Rakefile"], &["ruby"]),
+    ("Rust", &["rs"], &[], &[]),
+    ("SCSS", &["scss", "sass"], &[], &[]),
+    ("SQL", &["sql"], &[], &[]),
+    ("Scala", &["scala", "sc", "sbt"], &[], &["scala"]),
+    ("Shell", &["sh", "bash", "zsh", "ksh"], &[".bashrc", ".bash_profile", ".zshrc", ".profile"], &["sh", "bash", "zsh", "ksh", "dash"]),
+    ("Swift", &["swift"], &[], &[]),
+    ("TOML", &["toml"], &["Cargo.lock", "Pipfile"], &[]),
+    ("TypeScript", &["ts", "tsx", "mts", "cts", "d.ts"], &[], &["deno", "ts-node"]),
+    ("Vue", &["vue"], &[], &[]),
+    ("XML", &["xml", "xsd", "xsl", "xslt", "wsdl", "pom"], &[], &[]),
+    ("YAML", &["yaml", "yml"], &[".clang-format"], &[]),
+    ("reStructuredText", &["rst"], &[], &[]),
+    ("Batchfile", &["bat", "cmd"], &[], &[]),
+];
+
+/// Maps files to canonical language names, in the manner of GitHub linguist.
+///
+/// Exact file names win over extensions, and the longest extension wins, so
+/// `foo.d.ts` is checked as `d.ts` before `ts`. Files with neither can be
+/// recognised by the interpreter in their shebang line.
+#[derive(Debug, Clone)]
+pub struct LanguageRegistry {
+    // BTreeMaps so the Debug output, part of the cache fingerprint, is stable
+    extensions: BTreeMap<String, String>,
+    filenames: BTreeMap<String, String>,
+    interpreters: BTreeMap<String, String>,
+}
+
+impl Default for LanguageRegistry {
+    fn default() -> Self {
+        let mut registry = LanguageRegistry::empty();
+        for (language, extensions, filenames, interpreters) in BUILTIN_LANGUAGES.iter() {
+            for ext in extensions.iter() {
+                registry.add_extension(ext, language);
+            }
+            for filename in filenames.iter() {
+                registry.add_filename(filename, language);
+            }
+            for interpreter in interpreters.iter() {
+                registry.add_interpreter(interpreter, language);
+            }
+        }
+        registry
+    }
+}
+
+impl LanguageRegistry {
+
+    /// The built in languages.
+    pub fn new() -> LanguageRegistry {
+        LanguageRegistry::default()
+    }
+
+    /// A registry which recognises nothing.
+    pub fn empty() -> LanguageRegistry {
+        LanguageRegistry{extensions: BTreeMap::new(), filenames: BTreeMap::new(), interpreters: BTreeMap::new()}
+    }
+
+    /// Maps an extension, without the leading dot, to `language`.
+    pub fn add_extension(&mut self, extension: &str, language: &str) {
+        self.extensions.insert(extension.trim_start_matches(EXT_SPLIT).to_lowercase(), language.to_owned());
+    }
+
+    /// Maps an exact file name, such as `Makefile`, to `language`.
+    pub fn add_filename(&mut self, filename: &str, language: &str) {
+        self.filenames.insert(filename.to_owned(), language.to_owned());
+    }
+
+    /// Maps a shebang interpreter, such as `python3`, to `language`.
+    pub fn add_interpreter(&mut self, interpreter: &str, language: &str) {
+        self.interpreters.insert(interpreter.to_owned(), language.to_owned());
+    }
+
+    /// Every language the registry can detect.
+    pub fn languages(&self) -> Vec<&str> {
+        let mut languages: Vec<&str> = self.extensions.values()
+            .chain(self.filenames.values())
+            .chain(self.interpreters.values())
+            .map(|l| l.as_str())
+            .collect();
+        languages.sort_unstable();
+        languages.dedup();
+        languages
+    }
+
+    /// The language of `path` judging by its name alone.
+    pub fn detect(&self, path: &str) -> Option<&str> {
+        let filename = path.rsplit(PATH_SPLIT).next().unwrap_or(path);
+        if let Some(language) = self.filenames.get(filename) {
+            return Some(language);
+        }
+
+        // `.gitignore` is a file name with no extension, `foo.d.ts` has two
+        let name = filename.strip_prefix(EXT_SPLIT).unwrap_or(filename).to_lowercase();
+        let mut ext = name.as_str();
+        while let Some(i) = ext.find(EXT_SPLIT) {
+            ext = &ext[i + 1..];
+            if let Some(language) = self.extensions.get(ext) {
+                return Some(language);
+            }
+        }
+        if name.len() < filename.len() {
+            return self.extensions.get(name.as_str()).map(|l| l.as_str());
+        }
+        None
+    }
+
+    /// The language of a script judging by its first line, e.g. `#!/usr/bin/env python3`.
+    pub fn detect_shebang(&self, first_line: &str) -> Option<&str> {
+        let command = first_line.strip_prefix("#!")?.trim();
+        let mut words = command.split_whitespace();
+        let mut interpreter = words.next()?.rsplit(PATH_SPLIT).next()?;
+        if interpreter == "env" {
+            // skip options to env, e.g. `env -S deno run`
+            interpreter = words.find(|w| !w.starts_with('-'))?;
+        }
+        if let Some(language) = self.interpreters.get(interpreter) {
+            return Some(language);
+        }
+        // versioned interpreters such as python3.8
+        let unversioned = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+        self.interpreters.get(unversioned).map(|l| l.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_by_name() {
+        let registry = LanguageRegistry::new();
+        assert_eq!(registry.detect("component-a/src/test/java/Thing.java"), Some("Java"));
+        assert_eq!(registry.detect("build/Makefile"), Some("Makefile"));
+        assert_eq!(registry.detect("Dockerfile"), Some("Dockerfile"));
+        assert_eq!(registry.detect(".gitignore"), Some("Ignore List"));
+        assert_eq!(registry.detect("types/foo.d.ts"), Some("TypeScript"));
+        assert_eq!(registry.detect("deploy/values.yml"), registry.detect("deploy/values.yaml"));
+        assert_eq!(registry.detect("templates/nginx.conf.j2"), Some("Jinja"));
+        assert_eq!(registry.detect("bin/run"), None);
+    }
+
+    #[test]
+    fn test_detect_shebang() {
+        let registry = LanguageRegistry::new();
+        assert_eq!(registry.detect_shebang("#!/usr/bin/env python3"), Some("Python"));
+        assert_eq!(registry.detect_shebang("#!/usr/bin/python3.8"), Some("Python"));
+        assert_eq!(registry.detect_shebang("#!/bin/bash -e"), Some("Shell"));
+        assert_eq!(registry.detect_shebang("#!/usr/bin/env -S deno run"), Some("TypeScript"));
+        assert_eq!(registry.detect_shebang("print('hi')"), None);
+    }
+}
@@ -0,0 +1,139 @@
+/*
+   Copyright 2019-2020 foamdino@gmail.com
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, Default)]
+struct MailmapEntry {
+    proper_name: Option<String>,
+    proper_email: Option<String>,
+}
+
+/// Maps the identities recorded in commits to canonical ones, using the
+/// format of git's `.mailmap` file.
+#[derive(Debug, Clone, Default)]
+pub struct Mailmap {
+    // keyed by lowercased commit email, then by lowercased commit name where one
+    // was given, as git compares both case insensitively
+    by_email: HashMap<String, MailmapEntry>,
+    by_name_and_email: HashMap<(String, String), MailmapEntry>,
+}
+
+impl Mailmap {
+
+    pub fn new() -> Mailmap {
+        Mailmap::default()
+    }
+
+    /// Parses the contents of a mailmap file, lines which cannot be understood
+    /// are ignored as git does.
+    pub fn parse(contents: &str) -> Mailmap {
+        let mut mailmap = Mailmap::new();
+        for line in contents.lines() {
+            mailmap.add_line(line);
+        }
+        mailmap
+    }
+
+    /// Adds the entries of `other`, which win over any existing entries.
+    pub fn extend(&mut self, other: Mailmap) {
+        self.by_email.extend(other.by_email);
+        self.by_name_and_email.extend(other.by_name_and_email);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_email.is_empty() && self.by_name_and_email.is_empty()
+    }
+
+    /// Returns the canonical name and email for a commit identity.
+    pub fn resolve(&self, name: &str, email: &str) -> (String, String) {
+        let email_key = email.to_lowercase();
+        let entry = self.by_name_and_email.get(&(name.to_lowercase(), email_key.clone()))
+            .or_else(|| self.by_email.get(&email_key));
+
+        match entry {
+            Some(e) => (e.proper_name.clone().unwrap_or_else(|| name.to_owned()),
+                        e.proper_email.clone().unwrap_or_else(|| email.to_owned())),
+            None => (name.to_owned(), email.to_owned())
+        }
+    }
+
+    // every entry in a stable order, so equal mailmaps describe themselves equally
+    pub(crate) fn fingerprint(&self) -> String {
+        let by_email: BTreeMap<_, _> = self.by_email.iter().collect();
+        let by_name_and_email: BTreeMap<_, _> = self.by_name_and_email.iter().collect();
+        format!("{:?} {:?}", by_email, by_name_and_email)
+    }
+
+    fn add_line(&mut self, line: &str) {
+        let line = match line.find('#') {
+            Some(i) => &line[..i],
+            None => line
+        };
+
+        // a line is a sequence of optional names each followed by an <email>
+        let mut parts: Vec<(Option<String>, String)> = vec![];
+        let mut rest = line;
+        while let Some(open) = rest.find('<') {
+            let close = match rest[open..].find('>') {
+                Some(c) => open + c,
+                None => return
+            };
+            let name = rest[..open].trim();
+            let name = if name.is_empty() { None } else { Some(name.to_owned()) };
+            parts.push((name, rest[open + 1..close].trim().to_owned()));
+            rest = &rest[close + 1..];
+        }
+
+        let (proper, commit) = match parts.len() {
+            1 => ((parts[0].0.clone(), None), (None, parts[0].1.clone())),
+            2 => ((parts[0].0.clone(), Some(parts[0].1.clone())), parts[1].clone()),
+            _ => return
+        };
+        let entry = MailmapEntry{proper_name: proper.0, proper_email: proper.1};
+        if entry.proper_name.is_none() && entry.proper_email.is_none() {
+            return;
+        }
+
+        let email_key = commit.1.to_lowercase();
+        match commit.0 {
+            Some(commit_name) => { self.by_name_and_email.insert((commit_name.to_lowercase(), email_key), entry); }
+            None => { self.by_email.insert(email_key, entry); }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_mailmap_forms() {
+        let mailmap = Mailmap::parse("
+# comment
+Jane Doe <jane@example.com>
+<jane@example.com> <jane@old.example.com>
+Jane Doe <jane@example.com> <JDOE@laptop.local>
+Joe Bloggs <joe@example.com> joe <joe@laptop.local>
+");
+        assert_eq!(mailmap.resolve("jane", "jane@example.com"), ("Jane Doe".to_owned(), "jane@example.com".to_owned()));
+        assert_eq!(mailmap.resolve("Jane", "jane@old.example.com"), ("Jane".to_owned(), "jane@example.com".to_owned()));
+        assert_eq!(mailmap.resolve("jd", "jdoe@laptop.local"), ("Jane Doe".to_owned(), "jane@example.com".to_owned()));
+        assert_eq!(mailmap.resolve("joe", "joe@laptop.local"), ("Joe Bloggs".to_owned(), "joe@example.com".to_owned()));
+        assert_eq!(mailmap.resolve("Joe", "joe@laptop.local"), ("Joe Bloggs".to_owned(), "joe@example.com".to_owned()));
+        assert_eq!(mailmap.resolve("Joseph", "joe@laptop.local"), ("Joseph".to_owned(), "joe@laptop.local".to_owned()));
+    }
+}
@@ -0,0 +1,105 @@
+/*
+   Copyright 2019-2020 foamdino@gmail.com
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::fmt;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use crate::Error;
+
+/// A path pattern with gitignore semantics, as used by CODEOWNERS too.
+///
+/// `*` doesn't match `/`, a pattern without a `/` matches at any depth, a
+/// leading `/` anchors it to the root of the repository and a pattern which
+/// names a directory matches everything below it. Negated `!` patterns aren't
+/// supported, exclusions are given as patterns of their own instead.
+#[derive(Clone)]
+pub struct PathPattern {
+    pattern: String,
+    matcher: GlobSet,
+}
+
+impl PathPattern {
+
+    pub fn new(pattern: &str) -> Result<PathPattern, Error> {
+        if pattern.starts_with('!') {
+            return Err(Error::Config(format!("invalid pattern {}: negation isn't supported, exclude the path instead", pattern)));
+        }
+        let dir_only = pattern.ends_with('/');
+        let trimmed = pattern.trim_end_matches('/');
+        let glob = match trimmed.strip_prefix('/') {
+            Some(anchored) => anchored.to_owned(),
+            None if trimmed.contains('/') => trimmed.to_owned(),
+            None => format!("**/{}", trimmed)
+        };
+
+        let mut globs = vec![format!("{}/**", glob)];
+        if !dir_only {
+            globs.push(glob);
+        }
+
+        let mut builder = GlobSetBuilder::new();
+        for g in globs {
+            let glob = GlobBuilder::new(&g)
+                .literal_separator(true)
+                .build()
+                .map_err(|e| Error::Config(format!("invalid pattern {}: {}", pattern, e)))?;
+            builder.add(glob);
+        }
+        let matcher = builder.build()
+            .map_err(|e| Error::Config(format!("invalid pattern {}: {}", pattern, e)))?;
+        Ok(PathPattern{pattern: pattern.to_owned(), matcher})
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Whether `path`, relative to the root of the repository, matches.
+    pub fn matches(&self, path: &str) -> bool {
+        self.matcher.is_match(path)
+    }
+}
+
+impl fmt::Debug for PathPattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PathPattern({:?})", self.pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gitignore_semantics() {
+        let unanchored = PathPattern::new("*.lock").unwrap();
+        assert!(unanchored.matches("Cargo.lock"));
+        assert!(unanchored.matches("services/billing/yarn.lock"));
+
+        let anchored = PathPattern::new("/vendor").unwrap();
+        assert!(anchored.matches("vendor/lib/a.go"));
+        assert!(!anchored.matches("services/vendor/a.go"));
+
+        let nested = PathPattern::new("**/generated/**").unwrap();
+        assert!(nested.matches("a/b/generated/c.java"));
+
+        let single_level = PathPattern::new("services/*").unwrap();
+        assert!(single_level.matches("services/billing"));
+        assert!(single_level.matches("services/billing/src/main.rs"));
+        assert!(!single_level.matches("libs/core/src/lib.rs"));
+
+        assert!(matches!(PathPattern::new("!vendor/keep.go"), Err(Error::Config(_))));
+    }
+}
@@ -0,0 +1,174 @@
+/*
+   Copyright 2019-2020 foamdino@gmail.com
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::collections::{BTreeSet, HashMap};
+use crate::CommitChanges;
+
+const LARGEST_PRS: usize = 10;
+
+/// Everything the commits referencing a single PR changed.
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+pub struct PrStats {
+    num_commits: u32,
+    authors: BTreeSet<String>,
+    components: BTreeSet<String>,
+    #[serde(default)]
+    files: BTreeSet<String>,
+    changes: CommitChanges,
+}
+
+impl PrStats {
+
+    pub fn num_commits(&self) -> u32 {
+        self.num_commits
+    }
+
+    pub fn authors(&self) -> &BTreeSet<String> {
+        &self.authors
+    }
+
+    pub fn components(&self) -> &BTreeSet<String> {
+        &self.components
+    }
+
+    pub fn changes(&self) -> CommitChanges {
+        self.changes
+    }
+
+    /// Paths changed by any of the PR's commits.
+    pub fn files(&self) -> &BTreeSet<String> {
+        &self.files
+    }
+
+    /// Number of distinct files the PR changed, however many of its commits changed them.
+    pub fn files_changed(&self) -> u32 {
+        self.files.len() as u32
+    }
+
+    pub(crate) fn merge(&mut self, other: PrStats) {
+        self.num_commits += other.num_commits;
+        self.authors.extend(other.authors);
+        self.components.extend(other.components);
+        self.files.extend(other.files);
+        self.changes += other.changes;
+    }
+
+    pub(crate) fn record_commit<'a, I, F>(&mut self, author: &str, components: I, files: F, changes: CommitChanges)
+        where I: IntoIterator<Item = &'a String>,
+              F: IntoIterator<Item = &'a String> {
+        self.num_commits += 1;
+        if !self.authors.contains(author) {
+            self.authors.insert(author.to_owned());
+        }
+        self.components.extend(components.into_iter().cloned());
+        self.files.extend(files.into_iter().cloned());
+        self.changes += changes;
+    }
+}
+
+/// The distribution of PR sizes, in lines churned and files changed.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct PrSizes {
+    num_prs: u32,
+    median_churn: u32,
+    p90_churn: u32,
+    median_files: u32,
+    p90_files: u32,
+    largest: Vec<String>,
+}
+
+impl PrSizes {
+
+    pub(crate) fn new(prs: &HashMap<String, PrStats>) -> PrSizes {
+        let mut churn: Vec<u32> = prs.values().map(|pr| pr.changes().churn()).collect();
+        let mut files: Vec<u32> = prs.values().map(|pr| pr.files_changed()).collect();
+        churn.sort_unstable();
+        files.sort_unstable();
+
+        let mut by_size: Vec<(&String, u32)> = prs.iter().map(|(id, pr)| (id, pr.changes().churn())).collect();
+        by_size.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+        PrSizes{
+            num_prs: prs.len() as u32,
+            median_churn: percentile(&churn, 50),
+            p90_churn: percentile(&churn, 90),
+            median_files: percentile(&files, 50),
+            p90_files: percentile(&files, 90),
+            largest: by_size.into_iter().take(LARGEST_PRS).map(|(id, _)| id.clone()).collect(),
+        }
+    }
+
+    pub fn num_prs(&self) -> u32 {
+        self.num_prs
+    }
+
+    /// Median lines inserted plus deleted per PR.
+    pub fn median_churn(&self) -> u32 {
+        self.median_churn
+    }
+
+    pub fn p90_churn(&self) -> u32 {
+        self.p90_churn
+    }
+
+    /// Median files changed per PR.
+    pub fn median_files(&self) -> u32 {
+        self.median_files
+    }
+
+    pub fn p90_files(&self) -> u32 {
+        self.p90_files
+    }
+
+    /// IDs of the PRs with the most churn, largest first.
+    pub fn largest(&self) -> &[String] {
+        &self.largest
+    }
+}
+
+// nearest rank percentile of sorted values
+fn percentile(sorted: &[u32], p: usize) -> u32 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pr_sizes() {
+        let mut prs = HashMap::new();
+        let file = vec!["billing/Export.java".to_owned()];
+        for (n, lines) in (1..=10).enumerate() {
+            let mut pr = PrStats::default();
+            // two commits to the same file
+            for _ in 0..2 {
+                pr.record_commit("Jane <jane@example.com>", &[], &file, CommitChanges::new(0, 0, 1).with_lines(lines * 5, 0));
+            }
+            prs.insert(format!("#{}", n + 1), pr);
+        }
+        let sizes = PrSizes::new(&prs);
+        assert_eq!(sizes.num_prs(), 10);
+        assert_eq!(sizes.median_churn(), 50);
+        assert_eq!(sizes.p90_churn(), 90);
+        assert_eq!(sizes.median_files(), 1);
+        assert_eq!(&sizes.largest()[..2], &["#10".to_owned(), "#9".to_owned()]);
+    }
+}
@@ -0,0 +1,118 @@
+/*
+   Copyright 2019-2020 foamdino@gmail.com
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use chrono::Weekday;
+
+const DAYS: usize = 7;
+const HOURS: usize = 24;
+const DAY_NAMES: [&str; DAYS] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+// darker is busier, a blank cell had no commits at all
+const SHADES: [char; 5] = [' ', '░', '▒', '▓', '█'];
+
+/// Commits per hour of the day for each day of the week, Monday first.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Punchcard {
+    counts: Vec<Vec<u32>>,
+}
+
+impl Default for Punchcard {
+    fn default() -> Self {
+        Punchcard{counts: vec![vec![0; HOURS]; DAYS]}
+    }
+}
+
+impl Punchcard {
+
+    pub fn new() -> Punchcard {
+        Punchcard::default()
+    }
+
+    /// Commits in `hour`, from 0 to 23, on `day`.
+    pub fn get(&self, day: Weekday, hour: u32) -> u32 {
+        self.counts[day.num_days_from_monday() as usize][hour as usize % HOURS]
+    }
+
+    /// One row of 24 hourly counts per day, Monday first.
+    pub fn rows(&self) -> &[Vec<u32>] {
+        &self.counts
+    }
+
+    pub fn total(&self) -> u32 {
+        self.counts.iter().flatten().sum()
+    }
+
+    pub(crate) fn record(&mut self, day: Weekday, hour: u32) {
+        self.counts[day.num_days_from_monday() as usize][hour as usize % HOURS] += 1;
+    }
+
+    pub(crate) fn merge(&mut self, other: Punchcard) {
+        for (ours, theirs) in self.counts.iter_mut().zip(other.counts) {
+            for (count, other_count) in ours.iter_mut().zip(theirs) {
+                *count += other_count;
+            }
+        }
+    }
+
+    /// The punchcard as a text heatmap, one line per day and two characters per hour.
+    pub fn heatmap(&self) -> String {
+        let max = self.counts.iter().flatten().copied().max().unwrap_or(0);
+        let mut out = String::from("    ");
+        for hour in 0..HOURS {
+            if hour % 3 == 0 {
+                out.push_str(&format!("{:<6}", format!("{:02}", hour)));
+            }
+        }
+        out.push('\n');
+        for (day, hours) in DAY_NAMES.iter().zip(&self.counts) {
+            out.push_str(day);
+            out.push(' ');
+            for count in hours {
+                let shade = if *count == 0 {
+                    SHADES[0]
+                } else {
+                    // every non zero count gets at least the lightest shade
+                    SHADES[1 + ((*count - 1) as usize * (SHADES.len() - 1) / max as usize)]
+                };
+                out.push(shade);
+                out.push(shade);
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_heatmap() {
+        let mut punchcard = Punchcard::new();
+        for _ in 0..4 {
+            punchcard.record(Weekday::Fri, 17);
+        }
+        punchcard.record(Weekday::Mon, 9);
+        assert_eq!(punchcard.get(Weekday::Fri, 17), 4);
+        assert_eq!(punchcard.total(), 5);
+
+        let heatmap = punchcard.heatmap();
+        let friday = heatmap.lines().find(|l| l.starts_with("Fri")).unwrap();
+        assert_eq!(friday.chars().nth(4 + 2 * 17), Some('█'));
+        let monday = heatmap.lines().find(|l| l.starts_with("Mon")).unwrap();
+        assert_eq!(monday.chars().nth(4 + 2 * 9), Some('░'));
+    }
+}
@@ -0,0 +1,196 @@
+/*
+   Copyright 2019-2020 foamdino@gmail.com
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::fmt;
+use regex::Regex;
+use crate::Error;
+
+// squash merges, `Add thing (#12)`, but not `Revert "Add thing (#12)"`
+const SQUASH_PR: &str = r"\((#\d+)\)$";
+// merge commits made by GitHub
+const GITHUB_MERGE: &str = r"(?m)^Merge pull request (#\d+)";
+// merge commits made by GitLab, which give the ID in a trailer
+const GITLAB_MERGE: &str = r"(?m)^See merge request [\w./-]*(![\d]+)";
+// closing keywords as GitHub understands them, `Fixes #12`
+const CLOSING_KEYWORD: &str = r"(?i)\b(?:close[sd]?|fix(?:e[sd])?|resolve[sd]?):?\s+(#\d+)";
+
+/// What a reference in a commit message points at.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReferenceKind {
+    /// A pull or merge request the commit came from.
+    Pr,
+    /// An issue or ticket the commit mentions.
+    Issue,
+}
+
+/// A regex for one kind of reference, see `ReferenceExtractor`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReferenceRule {
+    pub kind: ReferenceKind,
+    pub pattern: String,
+}
+
+/// The PR and issue IDs mentioned in a commit message, in order of appearance.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct References {
+    pub prs: Vec<String>,
+    pub issues: Vec<String>,
+}
+
+/// Pulls PR and issue IDs out of commit messages with a list of regexes.
+///
+/// The ID is the first capture group of a match, or the whole match when the
+/// regex has no groups. PR rules are matched against the subject alone, so PRs
+/// listed in a squash commit's body aren't credited with its changes, and issue
+/// rules against the whole message. The built in rules recognise squash merge subjects
+/// (`Add thing (#12)`), GitHub and GitLab merge commits and closing keywords
+/// such as `Fixes #12`. Jira keys such as `ABC-123` are only recognised for the
+/// projects given to `add_jira_projects`, as the bare pattern matches names
+/// such as `UTF-8` too.
+#[derive(Clone)]
+pub struct ReferenceExtractor {
+    rules: Vec<Rule>,
+}
+
+#[derive(Clone)]
+struct Rule {
+    kind: ReferenceKind,
+    whole_message: bool,
+    regex: Regex,
+}
+
+impl Default for ReferenceExtractor {
+    fn default() -> Self {
+        let mut extractor = ReferenceExtractor::empty();
+        // GitLab's merge subject doesn't give the ID, so its rule reads the whole message
+        let builtin = [(ReferenceKind::Pr, false, SQUASH_PR),
+                       (ReferenceKind::Pr, false, GITHUB_MERGE),
+                       (ReferenceKind::Pr, true, GITLAB_MERGE),
+                       (ReferenceKind::Issue, true, CLOSING_KEYWORD)];
+        for (kind, whole_message, pattern) in builtin.iter() {
+            extractor.rules.push(Rule{kind: *kind, whole_message: *whole_message, regex: Regex::new(pattern).unwrap()});
+        }
+        extractor
+    }
+}
+
+impl ReferenceExtractor {
+
+    /// The built in rules.
+    pub fn new() -> ReferenceExtractor {
+        ReferenceExtractor::default()
+    }
+
+    /// An extractor which finds nothing.
+    pub fn empty() -> ReferenceExtractor {
+        ReferenceExtractor{rules: vec![]}
+    }
+
+    /// An extractor using only the given rules.
+    pub fn with_rules(rules: &[ReferenceRule]) -> Result<ReferenceExtractor, Error> {
+        let mut extractor = ReferenceExtractor::empty();
+        for rule in rules {
+            extractor.add_rule(rule.kind, &rule.pattern)?;
+        }
+        Ok(extractor)
+    }
+
+    pub fn add_rule(&mut self, kind: ReferenceKind, pattern: &str) -> Result<(), Error> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| Error::Config(format!("invalid reference pattern {}: {}", pattern, e)))?;
+        self.rules.push(Rule{kind, whole_message: kind == ReferenceKind::Issue, regex});
+        Ok(())
+    }
+
+    /// Recognises Jira keys, such as `ABC-123`, of the given projects as issues.
+    pub fn add_jira_projects(&mut self, projects: &[String]) -> Result<(), Error> {
+        if projects.is_empty() {
+            return Ok(());
+        }
+        let keys: Vec<String> = projects.iter().map(|p| regex::escape(p)).collect();
+        self.add_rule(ReferenceKind::Issue, &format!(r"\b((?:{})-\d+)\b", keys.join("|")))
+    }
+
+    /// The distinct references in `message`.
+    pub fn extract(&self, message: &str) -> References {
+        let subject = subject(message);
+        let mut references = References::default();
+        for rule in &self.rules {
+            let ids = match rule.kind {
+                ReferenceKind::Pr => &mut references.prs,
+                ReferenceKind::Issue => &mut references.issues,
+            };
+            let text = if rule.whole_message { message } else { &subject };
+            for captures in rule.regex.captures_iter(text) {
+                let id = captures.get(1).or_else(|| captures.get(0)).unwrap().as_str();
+                if !ids.iter().any(|i| i == id) {
+                    ids.push(id.to_owned());
+                }
+            }
+        }
+        // a PR number is not also an issue
+        let prs = &references.prs;
+        references.issues.retain(|i| !prs.contains(i));
+        references
+    }
+}
+
+impl fmt::Debug for ReferenceExtractor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rules: Vec<(ReferenceKind, bool, &str)> = self.rules.iter()
+            .map(|r| (r.kind, r.whole_message, r.regex.as_str()))
+            .collect();
+        f.debug_struct("ReferenceExtractor").field("rules", &rules).finish()
+    }
+}
+
+// the first paragraph of `message` on one line, as git takes the subject
+fn subject(message: &str) -> String {
+    message.trim_start().lines()
+        .take_while(|line| !line.trim().is_empty())
+        .map(|line| line.trim())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_rules() {
+        let mut extractor = ReferenceExtractor::new();
+        assert!(extractor.extract("Move UTF-8 handling next to SHA-256 (#41)").issues.is_empty());
+        extractor.add_jira_projects(&["PAY".to_owned()]).unwrap();
+        let squash = extractor.extract("Add billing export (#42)\n\nFixes #40, refs PAY-7 and UTF-8");
+        assert_eq!(squash.prs, vec!["#42"]);
+        assert_eq!(squash.issues, vec!["#40", "PAY-7"]);
+
+        let merge = extractor.extract("Merge pull request #3 from org/feature\n\nCloses #3");
+        assert_eq!(merge.prs, vec!["#3"]);
+        assert!(merge.issues.is_empty());
+
+        let listed = extractor.extract("Release 1.2 (#50)\n\n* fix export (#40)\n* tidy up (#41)");
+        assert_eq!(listed.prs, vec!["#50"]);
+        let revert = extractor.extract("Revert \"Add billing export (#42)\"\n\nThis reverts commit abc.");
+        assert!(revert.prs.is_empty());
+        let gitlab = extractor.extract("Merge branch 'export' into 'main'\n\nSee merge request billing/api!9");
+        assert_eq!(gitlab.prs, vec!["!9"]);
+
+        assert_eq!(extractor.extract("Tidy up"), References::default());
+    }
+}
@@ -0,0 +1,433 @@
+/*
+   Copyright 2019-2020 foamdino@gmail.com
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use crate::{CommitChanges, Stats};
+
+const DAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+const CHANGES_HEADERS: [&str; 7] = ["files added", "files deleted", "files modified", "files renamed", "files copied",
+                                    "lines inserted", "lines deleted"];
+
+/// The formats a `Stats` value can be rendered in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    PrettyJson,
+    Csv,
+    Markdown,
+    Table,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Format::Json),
+            "pretty-json" => Ok(Format::PrettyJson),
+            "csv" => Ok(Format::Csv),
+            "markdown" | "md" => Ok(Format::Markdown),
+            "table" => Ok(Format::Table),
+            _ => Err(format!("unknown format: {} (expected json, pretty-json, csv, markdown or table)", s))
+        }
+    }
+}
+
+impl Format {
+
+    /// Conventional file extension for the format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Json | Format::PrettyJson => "json",
+            Format::Csv => "csv",
+            Format::Markdown => "md",
+            Format::Table => "txt",
+        }
+    }
+}
+
+/// A titled table, the common shape behind the csv, markdown and text reports.
+#[derive(Debug, Clone)]
+pub struct Section {
+    title: String,
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Section {
+
+    fn new(title: &str, headers: Vec<String>) -> Section {
+        Section{title: title.to_owned(), headers, rows: vec![]}
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Section title as a file name friendly slug, e.g. `changes_by_component`.
+    pub fn name(&self) -> String {
+        self.title.to_lowercase().replace(' ', "_")
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut out = String::new();
+        for row in std::iter::once(&self.headers).chain(self.rows.iter()) {
+            let fields: Vec<String> = row.iter().map(|f| csv_field(f)).collect();
+            out.push_str(&fields.join(","));
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("## {}\n\n", self.title);
+        out.push_str(&format!("| {} |\n", self.headers.join(" | ")));
+        out.push_str(&format!("|{}|\n", self.headers.iter().map(|_| "---").collect::<Vec<_>>().join("|")));
+        for row in &self.rows {
+            let fields: Vec<String> = row.iter().map(|f| f.replace('|', "\\|")).collect();
+            out.push_str(&format!("| {} |\n", fields.join(" | ")));
+        }
+        out
+    }
+
+    pub fn to_table(&self) -> String {
+        let mut widths: Vec<usize> = self.headers.iter().map(|h| h.chars().count()).collect();
+        for row in &self.rows {
+            for (i, field) in row.iter().enumerate() {
+                widths[i] = widths[i].max(field.chars().count());
+            }
+        }
+        let rule = format!("+{}+\n", widths.iter().map(|w| "-".repeat(w + 2)).collect::<Vec<_>>().join("+"));
+        let line = |row: &Vec<String>| {
+            let cells: Vec<String> = row.iter().zip(&widths).map(|(f, w)| format!(" {:<w$} ", f, w = *w)).collect();
+            format!("|{}|\n", cells.join("|"))
+        };
+
+        let mut out = format!("{}\n", self.title);
+        out.push_str(&rule);
+        out.push_str(&line(&self.headers));
+        out.push_str(&rule);
+        for row in &self.rows {
+            out.push_str(&line(row));
+        }
+        out.push_str(&rule);
+        out
+    }
+}
+
+impl fmt::Display for Section {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_table())
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn headers(names: &[&str]) -> Vec<String> {
+    names.iter().map(|n| (*n).to_owned()).collect()
+}
+
+fn changes_columns(changes: &CommitChanges) -> Vec<String> {
+    vec![changes.files_added().to_string(),
+         changes.files_deleted().to_string(),
+         changes.files_modified().to_string(),
+         changes.files_renamed().to_string(),
+         changes.files_copied().to_string(),
+         changes.lines_inserted().to_string(),
+         changes.lines_deleted().to_string()]
+}
+
+fn sorted_keys<V>(map: &HashMap<String, V>) -> Vec<&String> {
+    let mut keys: Vec<&String> = map.keys().collect();
+    keys.sort();
+    keys
+}
+
+fn counts_and_changes_section(title: &str, key: &str, counts: &HashMap<String, u32>,
+                              changes: &HashMap<String, CommitChanges>) -> Section {
+    let mut names: Vec<&String> = counts.keys().chain(changes.keys()).collect();
+    names.sort();
+    names.dedup();
+
+    let mut section = Section::new(title, headers(&[key, "commits"]));
+    section.headers.extend(headers(&CHANGES_HEADERS));
+    for name in names {
+        let mut row = vec![name.clone(), counts.get(name).copied().unwrap_or(0).to_string()];
+        row.extend(changes_columns(&changes.get(name).copied().unwrap_or_else(|| CommitChanges::new(0, 0, 0))));
+        section.rows.push(row);
+    }
+    section
+}
+
+/// Splits `stats` into one table per map, in a stable order.
+pub fn sections(stats: &Stats) -> Vec<Section> {
+    let mut sections = vec![];
+
+    let mut summary = Section::new("Summary", headers(&["metric", "value"]));
+    summary.rows.push(vec!["commits".to_owned(), stats.num_commits_to_master().to_string()]);
+    summary.rows.push(vec!["prs".to_owned(), stats.num_prs().to_string()]);
+    summary.rows.push(vec!["missing prs".to_owned(), stats.missing_prs().to_string()]);
+    summary.rows.push(vec!["file changes".to_owned(), stats.num_file_changes().to_string()]);
+    summary.rows.push(vec!["timezone".to_owned(), stats.timezone().to_owned()]);
+    summary.rows.push(vec!["granularity".to_owned(), stats.commits_over_time().granularity().to_string()]);
+    summary.rows.push(vec!["merge strategy".to_owned(), stats.merge_strategy().to_string()]);
+    summary.rows.push(vec!["merges".to_owned(), stats.num_merges().to_string()]);
+    summary.rows.push(vec!["merge churn".to_owned(), stats.merge_changes().churn().to_string()]);
+    summary.rows.push(vec!["distinct prs".to_owned(), stats.prs().len().to_string()]);
+    summary.rows.push(vec!["distinct issues".to_owned(), stats.issues().len().to_string()]);
+    sections.push(summary);
+
+    sections.push(counts_and_changes_section("Changes by component", "component",
+                                             stats.component_stats(), stats.changes_by_component()));
+    let mut knowledge = Section::new("Knowledge by component",
+                                     headers(&["component", "authors", "top author", "top share",
+                                               "authors for 50%", "authors for 80%", "bus factor"]));
+    for comp_name in sorted_keys(stats.knowledge()) {
+        let k = &stats.knowledge()[comp_name];
+        let top = k.authors().first();
+        knowledge.rows.push(vec![comp_name.clone(),
+                                 k.authors().len().to_string(),
+                                 top.map(|a| a.author().to_owned()).unwrap_or_default(),
+                                 top.map(|a| format!("{:.0}%", a.share() * 100.0)).unwrap_or_default(),
+                                 k.authors_50().to_string(),
+                                 k.authors_80().to_string(),
+                                 k.bus_factor().to_string()]);
+    }
+    sections.push(knowledge);
+
+    sections.push(counts_and_changes_section("Changes by language", "language",
+                                             stats.lang_stats(), stats.changes_by_lang()));
+
+    let mut migrations = Section::new("Component migrations", headers(&["from", "to", "files"]));
+    for from in sorted_keys(stats.component_migrations()) {
+        let moves = &stats.component_migrations()[from];
+        for to in sorted_keys(moves) {
+            migrations.rows.push(vec![from.clone(), to.clone(), moves[to].to_string()]);
+        }
+    }
+    sections.push(migrations);
+
+    let mut over_time = Section::new("Commits over time", headers(&["period", "commits", "churn"]));
+    for ((period, commits), (_, churn)) in stats.commits_over_time().labelled().zip(stats.churn_over_time().labelled()) {
+        over_time.rows.push(vec![period, commits.to_string(), churn.to_string()]);
+    }
+    sections.push(over_time);
+
+    let mut by_day = Section::new("Commits by day of week", headers(&["day", "commits"]));
+    for day in DAYS.iter() {
+        let count = stats.commits_by_day_of_week().get(*day).copied().unwrap_or(0);
+        by_day.rows.push(vec![(*day).to_owned(), count.to_string()]);
+    }
+    sections.push(by_day);
+
+    let mut punchcard = Section::new("Punchcard", headers(&["day"]));
+    punchcard.headers.extend((0..24).map(|h| format!("{:02}", h)));
+    for (day, hours) in DAYS.iter().zip(stats.punchcard().rows()) {
+        let mut row = vec![(*day).to_owned()];
+        row.extend(hours.iter().map(|c| c.to_string()));
+        punchcard.rows.push(row);
+    }
+    sections.push(punchcard);
+
+    let mut by_component = Section::new("Punchcard by component", headers(&["component", "day"]));
+    by_component.headers.extend((0..24).map(|h| format!("{:02}", h)));
+    for comp_name in sorted_keys(stats.punchcards_by_component()) {
+        for (day, hours) in DAYS.iter().zip(stats.punchcards_by_component()[comp_name].rows()) {
+            let mut row = vec![comp_name.clone(), (*day).to_owned()];
+            row.extend(hours.iter().map(|c| c.to_string()));
+            by_component.rows.push(row);
+        }
+    }
+    sections.push(by_component);
+
+    let mut changes_over_time = Section::new("Changes over time", headers(&["period"]));
+    changes_over_time.headers.extend(headers(&CHANGES_HEADERS));
+    for (period, changes) in stats.changes_over_time().labelled() {
+        let mut row = vec![period];
+        row.extend(changes_columns(&changes));
+        changes_over_time.rows.push(row);
+    }
+    sections.push(changes_over_time);
+
+    let mut authors = Section::new("Authors", headers(&["author", "commits", "prs", "missing prs"]));
+    authors.headers.extend(headers(&CHANGES_HEADERS));
+    authors.headers.extend(headers(&["first commit", "last commit"]));
+    for name in sorted_keys(stats.authors()) {
+        let author = &stats.authors()[name];
+        let mut row = vec![name.clone(),
+                           author.num_commits().to_string(),
+                           author.num_prs().to_string(),
+                           author.missing_prs().to_string()];
+        row.extend(changes_columns(&author.changes()));
+        row.push(author.first_commit().format("%Y-%m-%d").to_string());
+        row.push(author.last_commit().format("%Y-%m-%d").to_string());
+        authors.rows.push(row);
+    }
+    sections.push(authors);
+
+    let mut hotspots = Section::new("Hotspots", headers(&["file", "changes", "churn", "score", "authors", "last modified"]));
+    for hotspot in stats.hotspots() {
+        let file = stats.files().get(hotspot.path());
+        hotspots.rows.push(vec![hotspot.path().to_owned(),
+                                hotspot.num_changes().to_string(),
+                                hotspot.churn().to_string(),
+                                hotspot.score().to_string(),
+                                file.map(|f| f.authors().len()).unwrap_or(0).to_string(),
+                                file.map(|f| f.last_modified().format("%Y-%m-%d").to_string()).unwrap_or_default()]);
+    }
+    sections.push(hotspots);
+
+    for (title, coupled) in [("Coupled components", stats.coupled_components()), ("Coupled files", stats.coupled_files())] {
+        let mut section = Section::new(title, headers(&["a", "b", "co-changes", "support", "confidence"]));
+        for c in coupled {
+            section.rows.push(vec![c.a().to_owned(),
+                                   c.b().to_owned(),
+                                   c.co_changes().to_string(),
+                                   format!("{:.3}", c.support()),
+                                   format!("{:.3}", c.confidence())]);
+        }
+        sections.push(section);
+    }
+
+    if let Some(code_age) = stats.code_age() {
+        for (title, key, by_key) in [("Code age by component", "component", code_age.by_component()),
+                                     ("Code age by language", "language", code_age.by_lang())] {
+            let mut years: Vec<&String> = by_key.values().flat_map(|ages| ages.by_year().keys()).collect();
+            years.sort();
+            years.dedup();
+            let mut section = Section::new(title, headers(&[key, "lines"]));
+            section.headers.extend(years.iter().map(|y| (*y).clone()));
+            for name in sorted_keys(by_key) {
+                let ages = &by_key[name];
+                let mut row = vec![name.clone(), ages.lines().to_string()];
+                row.extend(years.iter().map(|y| ages.by_year().get(*y).copied().unwrap_or(0).to_string()));
+                section.rows.push(row);
+            }
+            sections.push(section);
+        }
+
+        let mut by_author = Section::new("Surviving lines by author", headers(&["component", "author", "lines", "share"]));
+        for comp_name in sorted_keys(code_age.by_component()) {
+            let ages = &code_age.by_component()[comp_name];
+            for author in sorted_keys(ages.by_author()) {
+                let lines = ages.by_author()[author];
+                by_author.rows.push(vec![comp_name.clone(),
+                                         author.clone(),
+                                         lines.to_string(),
+                                         format!("{:.0}%", f64::from(lines) * 100.0 / f64::from(ages.lines().max(1)))]);
+            }
+        }
+        sections.push(by_author);
+    }
+
+    let mut prs = Section::new("Pull requests", headers(&["pr", "commits", "authors", "components", "files"]));
+    prs.headers.extend(headers(&CHANGES_HEADERS));
+    for id in sorted_keys(stats.prs()) {
+        let pr = &stats.prs()[id];
+        let mut row = vec![id.clone(),
+                           pr.num_commits().to_string(),
+                           pr.authors().iter().cloned().collect::<Vec<_>>().join("; "),
+                           pr.components().iter().cloned().collect::<Vec<_>>().join("; "),
+                           pr.files_changed().to_string()];
+        row.extend(changes_columns(&pr.changes()));
+        prs.rows.push(row);
+    }
+    sections.push(prs);
+
+    let sizes = stats.pr_sizes();
+    let mut pr_sizes = Section::new("PR sizes", headers(&["metric", "value"]));
+    pr_sizes.rows.push(vec!["prs".to_owned(), sizes.num_prs().to_string()]);
+    pr_sizes.rows.push(vec!["median churn".to_owned(), sizes.median_churn().to_string()]);
+    pr_sizes.rows.push(vec!["p90 churn".to_owned(), sizes.p90_churn().to_string()]);
+    pr_sizes.rows.push(vec!["median files".to_owned(), sizes.median_files().to_string()]);
+    pr_sizes.rows.push(vec!["p90 files".to_owned(), sizes.p90_files().to_string()]);
+    pr_sizes.rows.push(vec!["largest".to_owned(), sizes.largest().join(" ")]);
+    sections.push(pr_sizes);
+
+    let mut issues = Section::new("Issues", headers(&["issue", "commits"]));
+    for id in sorted_keys(stats.issues()) {
+        issues.rows.push(vec![id.clone(), stats.issues()[id].to_string()]);
+    }
+    sections.push(issues);
+
+    if !stats.skipped().is_empty() {
+        let mut skipped = Section::new("Skipped", headers(&["commit", "path", "reason"]));
+        for s in stats.skipped() {
+            skipped.rows.push(vec![s.commit().unwrap_or("").to_owned(),
+                                   s.path().unwrap_or("").to_owned(),
+                                   s.reason().to_owned()]);
+        }
+        sections.push(skipped);
+    }
+
+    sections
+}
+
+// the punchcards as text heatmaps, overall and then per component
+fn heatmaps(stats: &Stats) -> String {
+    let mut out = format!("Punchcard heatmap\n{}", stats.punchcard().heatmap());
+    for comp_name in sorted_keys(stats.punchcards_by_component()) {
+        out.push_str(&format!("\nPunchcard heatmap: {}\n{}", comp_name,
+                              stats.punchcards_by_component()[comp_name].heatmap()));
+    }
+    out
+}
+
+/// Renders `stats` in the given format.
+pub fn render(stats: &Stats, format: Format) -> Result<String, serde_json::Error> {
+    let joined = |f: fn(&Section) -> String| {
+        sections(stats).iter().map(f).collect::<Vec<String>>().join("\n")
+    };
+
+    match format {
+        Format::Json => serde_json::to_string(stats),
+        Format::PrettyJson => serde_json::to_string_pretty(stats),
+        Format::Csv => Ok(sections(stats).iter()
+            .map(|s| format!("# {}\n{}", s.title(), s.to_csv()))
+            .collect::<Vec<String>>().join("\n")),
+        Format::Markdown => Ok(format!("# Commit analysis\n\n{}", joined(Section::to_markdown))),
+        Format::Table => Ok(format!("{}\n{}", joined(Section::to_table), heatmaps(stats))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field_quoting() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("Doe, Jane"), "\"Doe, Jane\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_table_aligns_columns() {
+        let mut section = Section::new("Test", headers(&["a", "bb"]));
+        section.rows.push(vec!["long value".to_owned(), "1".to_owned()]);
+        let table = section.to_table();
+        assert!(table.contains("| a          | bb |"));
+        assert!(table.contains("| long value | 1  |"));
+    }
+}
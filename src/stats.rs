@@ -15,32 +15,85 @@
 */
 
 use std::collections::HashMap;
-use crate::CommitChanges;
+use std::ops::{Add, AddAssign};
+use crate::{AuthorStats, CodeAge, Granularity, Punchcard, TimeSeries, CommitChanges, ComponentResolver, Coupling, CouplingMatrix, FileStats, Knowledge, Hotspot, MergeStrategy, PrSizes, PrStats, Skipped};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct Stats {
-    num_commits_to_master: u32,
-    num_prs: u32,
-    missing_prs: u32,
-    num_file_changes: u32,
-    component_stats: HashMap<String, u32>,
-    lang_stats: HashMap<String, u32>,
-    commits_by_month: HashMap<String, Vec<u32>>,
-    commits_by_day_of_week: HashMap<String, u32>,
-    changes_by_component: HashMap<String, CommitChanges>,
+    pub(crate) num_commits_to_master: u32,
+    pub(crate) num_prs: u32,
+    pub(crate) missing_prs: u32,
+    pub(crate) num_file_changes: u32,
+    #[serde(default)]
+    pub(crate) timezone: String,
+    #[serde(default)]
+    pub(crate) merge_strategy: MergeStrategy,
+    #[serde(default)]
+    pub(crate) num_merges: u32,
+    #[serde(default)]
+    pub(crate) merge_changes: CommitChanges,
+    pub(crate) component_stats: HashMap<String, u32>,
+    pub(crate) lang_stats: HashMap<String, u32>,
+    #[serde(default)]
+    pub(crate) commits_over_time: TimeSeries<u32>,
+    pub(crate) commits_by_day_of_week: HashMap<String, u32>,
+    #[serde(default)]
+    pub(crate) punchcard: Punchcard,
+    #[serde(default)]
+    pub(crate) punchcards_by_component: HashMap<String, Punchcard>,
+    pub(crate) changes_by_component: HashMap<String, CommitChanges>,
+    #[serde(default)]
+    pub(crate) component_authors: HashMap<String, HashMap<String, u32>>,
+    #[serde(default)]
+    pub(crate) knowledge: HashMap<String, Knowledge>,
+    pub(crate) changes_by_lang: HashMap<String, CommitChanges>,
+    #[serde(default)]
+    pub(crate) changes_over_time: TimeSeries<CommitChanges>,
+    #[serde(default)]
+    pub(crate) churn_over_time: TimeSeries<u32>,
+    #[serde(default)]
+    pub(crate) component_migrations: HashMap<String, HashMap<String, u32>>,
+    pub(crate) authors: HashMap<String, AuthorStats>,
+    #[serde(default)]
+    pub(crate) component_coupling: CouplingMatrix,
+    #[serde(default)]
+    pub(crate) coupled_components: Vec<Coupling>,
+    #[serde(default)]
+    pub(crate) file_coupling: CouplingMatrix,
+    #[serde(default)]
+    pub(crate) coupled_files: Vec<Coupling>,
+    #[serde(default)]
+    pub(crate) files: HashMap<String, FileStats>,
+    #[serde(default)]
+    pub(crate) hotspots: Vec<Hotspot>,
+    #[serde(default)]
+    pub(crate) prs: HashMap<String, PrStats>,
+    #[serde(default)]
+    pub(crate) pr_sizes: PrSizes,
+    #[serde(default)]
+    pub(crate) issues: HashMap<String, u32>,
+    #[serde(default)]
+    pub(crate) code_age: Option<CodeAge>,
+    #[serde(default)]
+    pub(crate) skipped: Vec<Skipped>,
 }
 
 impl Stats {
 
+    #[allow(clippy::too_many_arguments)]
     pub fn new(num_commits_to_master: u32,
                num_prs: u32,
                missing_prs: u32,
                num_file_changes: u32,
                component_stats: HashMap<String, u32>,
                lang_stats: HashMap<String, u32>,
-               commits_by_month: HashMap<String, Vec<u32>>,
+               commits_over_time: TimeSeries<u32>,
                commits_by_day_of_week: HashMap<String, u32>,
-               changes_by_component: HashMap<String, CommitChanges>) -> Stats {
+               changes_by_component: HashMap<String, CommitChanges>,
+               changes_by_lang: HashMap<String, CommitChanges>,
+               changes_over_time: TimeSeries<CommitChanges>,
+               authors: HashMap<String, AuthorStats>,
+               skipped: Vec<Skipped>) -> Stats {
 
         Stats{
             num_commits_to_master,
@@ -49,9 +102,325 @@ impl Stats {
             num_file_changes,
             component_stats,
             lang_stats,
-            commits_by_month,
+            commits_over_time,
             commits_by_day_of_week,
-            changes_by_component
+            changes_by_component,
+            changes_by_lang,
+            changes_over_time,
+            authors,
+            skipped,
+            ..Default::default()
         }
     }
-}
\ No newline at end of file
+
+    // empty stats counting over time by `granularity`
+    pub(crate) fn with_granularity(granularity: Granularity) -> Stats {
+        Stats{
+            commits_over_time: TimeSeries::new(granularity),
+            changes_over_time: TimeSeries::new(granularity),
+            churn_over_time: TimeSeries::new(granularity),
+            ..Default::default()
+        }
+    }
+
+    pub fn num_commits_to_master(&self) -> u32 {
+        self.num_commits_to_master
+    }
+
+    pub fn num_prs(&self) -> u32 {
+        self.num_prs
+    }
+
+    pub fn missing_prs(&self) -> u32 {
+        self.missing_prs
+    }
+
+    pub fn num_file_changes(&self) -> u32 {
+        self.num_file_changes
+    }
+
+    /// The timezone commits were bucketed in, `author`, `utc` or an IANA name.
+    pub fn timezone(&self) -> &str {
+        &self.timezone
+    }
+
+    /// How merge commits were counted.
+    pub fn merge_strategy(&self) -> MergeStrategy {
+        self.merge_strategy
+    }
+
+    /// Number of merge commits walked, whatever the merge strategy.
+    pub fn num_merges(&self) -> u32 {
+        self.num_merges
+    }
+
+    /// Changes brought in by merges, against their first parent, when they
+    /// are counted separately.
+    pub fn merge_changes(&self) -> CommitChanges {
+        self.merge_changes
+    }
+
+    /// Number of commits touching each component.
+    pub fn component_stats(&self) -> &HashMap<String, u32> {
+        &self.component_stats
+    }
+
+    /// Number of commits touching each language.
+    pub fn lang_stats(&self) -> &HashMap<String, u32> {
+        &self.lang_stats
+    }
+
+    /// Commits per period, see `AnalysisOptions::granularity`.
+    pub fn commits_over_time(&self) -> &TimeSeries<u32> {
+        &self.commits_over_time
+    }
+
+    pub fn commits_by_day_of_week(&self) -> &HashMap<String, u32> {
+        &self.commits_by_day_of_week
+    }
+
+    /// Commits by weekday and hour of the day.
+    pub fn punchcard(&self) -> &Punchcard {
+        &self.punchcard
+    }
+
+    /// Commits touching each component by weekday and hour of the day.
+    pub fn punchcards_by_component(&self) -> &HashMap<String, Punchcard> {
+        &self.punchcards_by_component
+    }
+
+    pub fn changes_by_component(&self) -> &HashMap<String, CommitChanges> {
+        &self.changes_by_component
+    }
+
+    /// File changes to each component by each author, keyed by component.
+    pub fn component_authors(&self) -> &HashMap<String, HashMap<String, u32>> {
+        &self.component_authors
+    }
+
+    /// How concentrated the knowledge of each component is, as of the end of
+    /// the analysis.
+    pub fn knowledge(&self) -> &HashMap<String, Knowledge> {
+        &self.knowledge
+    }
+
+    pub fn changes_by_lang(&self) -> &HashMap<String, CommitChanges> {
+        &self.changes_by_lang
+    }
+
+    /// File and line changes per period.
+    pub fn changes_over_time(&self) -> &TimeSeries<CommitChanges> {
+        &self.changes_over_time
+    }
+
+    /// Lines inserted plus lines deleted per period.
+    pub fn churn_over_time(&self) -> &TimeSeries<u32> {
+        &self.churn_over_time
+    }
+
+    /// Files renamed from one component into another, keyed by the component
+    /// they left and then the one they moved to.
+    pub fn component_migrations(&self) -> &HashMap<String, HashMap<String, u32>> {
+        &self.component_migrations
+    }
+
+    /// Per author breakdown, keyed by the canonical `name <email>` after
+    /// applying the mailmap.
+    pub fn authors(&self) -> &HashMap<String, AuthorStats> {
+        &self.authors
+    }
+
+    /// Number of commits changing each pair of components.
+    pub fn component_coupling(&self) -> &CouplingMatrix {
+        &self.component_coupling
+    }
+
+    /// The most strongly coupled components, as of the end of the analysis.
+    pub fn coupled_components(&self) -> &[Coupling] {
+        &self.coupled_components
+    }
+
+    /// Number of commits changing each pair of files, when file coupling is on.
+    pub fn file_coupling(&self) -> &CouplingMatrix {
+        &self.file_coupling
+    }
+
+    /// The most strongly coupled files, as of the end of the analysis.
+    pub fn coupled_files(&self) -> &[Coupling] {
+        &self.coupled_files
+    }
+
+    /// How each file changed, keyed by its path. Files are tracked by the path
+    /// they were changed under, so history from before a rename isn't carried over.
+    pub fn files(&self) -> &HashMap<String, FileStats> {
+        &self.files
+    }
+
+    /// The most frequently and heavily changed files, as of the end of the analysis.
+    pub fn hotspots(&self) -> &[Hotspot] {
+        &self.hotspots
+    }
+
+    /// What the commits referencing each PR changed, keyed by its ID, e.g. `#12`.
+    pub fn prs(&self) -> &HashMap<String, PrStats> {
+        &self.prs
+    }
+
+    /// The distribution of PR sizes, as of the end of the analysis.
+    pub fn pr_sizes(&self) -> &PrSizes {
+        &self.pr_sizes
+    }
+
+    /// Number of commits referencing each issue, keyed by its ID, e.g. `#12` or `ABC-123`.
+    pub fn issues(&self) -> &HashMap<String, u32> {
+        &self.issues
+    }
+
+    /// Adds everything counted in `other` to these stats: counts are summed
+    /// and maps are unioned.
+    pub fn merge(&mut self, other: Stats) {
+        self.num_commits_to_master += other.num_commits_to_master;
+        self.num_prs += other.num_prs;
+        self.missing_prs += other.missing_prs;
+        self.num_file_changes += other.num_file_changes;
+        self.num_merges += other.num_merges;
+        self.merge_changes += other.merge_changes;
+        merge_counts(&mut self.component_stats, other.component_stats);
+        merge_counts(&mut self.lang_stats, other.lang_stats);
+        self.commits_over_time.merge(other.commits_over_time);
+        merge_counts(&mut self.commits_by_day_of_week, other.commits_by_day_of_week);
+        self.punchcard.merge(other.punchcard);
+        for (comp_name, punchcard) in other.punchcards_by_component {
+            self.punchcards_by_component.entry(comp_name).or_default().merge(punchcard);
+        }
+        merge_changes(&mut self.changes_by_component, other.changes_by_component);
+        merge_changes(&mut self.changes_by_lang, other.changes_by_lang);
+        self.changes_over_time.merge(other.changes_over_time);
+        self.churn_over_time.merge(other.churn_over_time);
+        merge_nested_counts(&mut self.component_migrations, other.component_migrations);
+        merge_nested_counts(&mut self.component_coupling, other.component_coupling);
+        merge_nested_counts(&mut self.component_authors, other.component_authors);
+        merge_nested_counts(&mut self.file_coupling, other.file_coupling);
+        for (name, author) in other.authors {
+            match self.authors.get_mut(&name) {
+                Some(ours) => ours.merge(author),
+                None => { self.authors.insert(name, author); }
+            }
+        }
+        for (path, file) in other.files {
+            match self.files.get_mut(&path) {
+                Some(ours) => ours.merge(file),
+                None => { self.files.insert(path, file); }
+            }
+        }
+        for (id, pr) in other.prs {
+            self.prs.entry(id).or_default().merge(pr);
+        }
+        merge_counts(&mut self.issues, other.issues);
+        match (&mut self.code_age, other.code_age) {
+            (Some(ours), Some(theirs)) => ours.merge(theirs),
+            (ours @ None, theirs) => *ours = theirs,
+            _ => ()
+        }
+        self.skipped.extend(other.skipped);
+    }
+
+    /// Surviving lines by author and year, when a code age revision was given.
+    pub fn code_age(&self) -> Option<&CodeAge> {
+        self.code_age.as_ref()
+    }
+
+    /// Works out the summaries derived from the merged counts, which `merge`
+    /// leaves stale.
+    pub(crate) fn summarise(&mut self, top_n: usize, components: &dyn ComponentResolver) {
+        self.commits_over_time.fill();
+        self.changes_over_time.fill();
+        self.churn_over_time.fill();
+        self.hotspots = Hotspot::rank(&self.files, top_n);
+        self.coupled_components = Coupling::rank(&self.component_coupling, &self.component_stats,
+                                                 self.num_commits_to_master, top_n);
+        let file_changes: HashMap<String, u32> = self.files.iter()
+            .filter(|(_, file)| file.exists())
+            .map(|(path, file)| (path.clone(), file.num_changes()))
+            .collect();
+        self.coupled_files = Coupling::rank(&self.file_coupling, &file_changes, self.num_commits_to_master, top_n);
+        self.pr_sizes = PrSizes::new(&self.prs);
+
+        let mut main_authors: HashMap<String, Vec<&str>> = HashMap::new();
+        for (path, file) in self.files.iter().filter(|(_, file)| file.exists()) {
+            if let Some(author) = file.main_author() {
+                let comp_name = components.component(path).unwrap_or_else(|| "unknown".to_owned());
+                main_authors.entry(comp_name).or_default().push(author);
+            }
+        }
+        self.knowledge = self.component_authors.iter()
+            .map(|(comp_name, changes)| {
+                let owners = main_authors.get(comp_name).map(|o| o.as_slice()).unwrap_or(&[]);
+                (comp_name.clone(), Knowledge::new(changes, owners.iter().copied()))
+            })
+            .collect();
+    }
+
+    /// Commits and paths left out of a lenient analysis, with the reason why.
+    pub fn skipped(&self) -> &[Skipped] {
+        &self.skipped
+    }
+}
+
+impl Add for Stats {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        self.merge(other);
+        self
+    }
+}
+
+impl AddAssign for Stats {
+    fn add_assign(&mut self, other: Stats) {
+        self.merge(other);
+    }
+}
+
+pub(crate) fn merge_counts(ours: &mut HashMap<String, u32>, theirs: HashMap<String, u32>) {
+    for (key, count) in theirs {
+        *ours.entry(key).or_insert(0) += count;
+    }
+}
+
+fn merge_nested_counts(ours: &mut HashMap<String, HashMap<String, u32>>, theirs: HashMap<String, HashMap<String, u32>>) {
+    for (key, counts) in theirs {
+        merge_counts(ours.entry(key).or_default(), counts);
+    }
+}
+
+fn merge_changes(ours: &mut HashMap<String, CommitChanges>, theirs: HashMap<String, CommitChanges>) {
+    for (key, changes) in theirs {
+        *ours.entry(key).or_insert_with(|| CommitChanges::new(0, 0, 0)) += changes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_merge_sums_counts_and_unions_maps() {
+        let mut a = Stats{num_commits_to_master: 2, ..Default::default()};
+        a.component_stats.insert("component-a".to_owned(), 2);
+        let date = NaiveDate::from_ymd_opt(2020, 3, 30).unwrap();
+        a.commits_over_time.record(date, 1);
+
+        let mut b = Stats{num_commits_to_master: 3, ..Default::default()};
+        b.component_stats.insert("component-a".to_owned(), 1);
+        b.component_stats.insert("component-b".to_owned(), 4);
+        b.commits_over_time.record(date, 2);
+
+        let merged = a + b;
+        assert_eq!(merged.num_commits_to_master(), 5);
+        assert_eq!(merged.component_stats()["component-a"], 3);
+        assert_eq!(merged.component_stats()["component-b"], 4);
+        assert_eq!(merged.commits_over_time().labelled().collect::<Vec<_>>(), vec![("2020-03".to_owned(), 3)]);
+    }
+}
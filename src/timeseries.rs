@@ -0,0 +1,185 @@
+/*
+   Copyright 2019-2020 foamdino@gmail.com
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::AddAssign;
+use std::str::FromStr;
+use chrono::{Datelike, Duration, Months, NaiveDate};
+
+/// The length of the periods a `TimeSeries` counts over.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Granularity {
+    Day,
+    /// ISO 8601 weeks, starting on Monday.
+    Week,
+    #[default]
+    Month,
+    Quarter,
+    Year,
+}
+
+impl Granularity {
+
+    /// The first day of the period `date` falls in.
+    pub fn period_start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Granularity::Day => date,
+            Granularity::Week => date - Duration::days(i64::from(date.weekday().num_days_from_monday())),
+            Granularity::Month => date.with_day(1).unwrap_or(date),
+            Granularity::Quarter => NaiveDate::from_ymd_opt(date.year(), (date.month() - 1) / 3 * 3 + 1, 1)
+                .unwrap_or(date),
+            Granularity::Year => NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap_or(date),
+        }
+    }
+
+    /// The first day of the period after the one starting on `start`.
+    fn next(self, start: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Granularity::Day => start.succ_opt(),
+            Granularity::Week => start.checked_add_signed(Duration::days(7)),
+            Granularity::Month => start.checked_add_months(Months::new(1)),
+            Granularity::Quarter => start.checked_add_months(Months::new(3)),
+            Granularity::Year => start.checked_add_months(Months::new(12)),
+        }
+    }
+
+    /// A name for the period starting on `start`, e.g. `2020-03-30`, `2020-W14`,
+    /// `2020-03`, `2020-Q1` or `2020`.
+    pub fn label(self, start: NaiveDate) -> String {
+        match self {
+            Granularity::Day => start.format("%Y-%m-%d").to_string(),
+            Granularity::Week => {
+                let week = start.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            },
+            Granularity::Month => start.format("%Y-%m").to_string(),
+            Granularity::Quarter => format!("{}-Q{}", start.year(), (start.month() - 1) / 3 + 1),
+            Granularity::Year => start.year().to_string(),
+        }
+    }
+}
+
+impl FromStr for Granularity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "day" => Ok(Granularity::Day),
+            "week" => Ok(Granularity::Week),
+            "month" => Ok(Granularity::Month),
+            "quarter" => Ok(Granularity::Quarter),
+            "year" => Ok(Granularity::Year),
+            _ => Err(format!("unknown granularity: {} (expected day, week, month, quarter or year)", s))
+        }
+    }
+}
+
+impl fmt::Display for Granularity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Granularity::Day => "day",
+            Granularity::Week => "week",
+            Granularity::Month => "month",
+            Granularity::Quarter => "quarter",
+            Granularity::Year => "year",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Values summed per period, keyed by the first day of each period. Once
+/// filled the periods run without gaps from the first to the last one counted.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct TimeSeries<T> {
+    granularity: Granularity,
+    periods: BTreeMap<NaiveDate, T>,
+}
+
+impl<T: Copy + Default + AddAssign> TimeSeries<T> {
+
+    pub fn new(granularity: Granularity) -> TimeSeries<T> {
+        TimeSeries{granularity, periods: BTreeMap::new()}
+    }
+
+    pub fn granularity(&self) -> Granularity {
+        self.granularity
+    }
+
+    /// The value for each period, oldest first.
+    pub fn periods(&self) -> &BTreeMap<NaiveDate, T> {
+        &self.periods
+    }
+
+    /// Each period's label, see `Granularity::label`, with its value, oldest first.
+    pub fn labelled(&self) -> impl Iterator<Item = (String, T)> + '_ {
+        self.periods.iter().map(move |(start, value)| (self.granularity.label(*start), *value))
+    }
+
+    /// Adds `value` to the period `date` falls in.
+    pub(crate) fn record(&mut self, date: NaiveDate, value: T) {
+        *self.periods.entry(self.granularity.period_start(date)).or_default() += value;
+    }
+
+    // periods of another granularity are counted in the period they start in
+    pub(crate) fn merge(&mut self, other: TimeSeries<T>) {
+        for (start, value) in other.periods {
+            self.record(start, value);
+        }
+    }
+
+    /// Adds the missing periods between the first and the last, with nothing counted.
+    pub(crate) fn fill(&mut self) {
+        let (first, last) = match (self.periods.keys().next(), self.periods.keys().next_back()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return
+        };
+        let mut start = first;
+        while start < last {
+            self.periods.entry(start).or_default();
+            start = match self.granularity.next(start) {
+                Some(next) => next,
+                None => break
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_orders_and_zero_fills() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let mut series = TimeSeries::new(Granularity::Week);
+        series.record(date(2021, 1, 6), 2u32);
+        series.record(date(2020, 12, 29), 1);
+        series.record(date(2020, 12, 31), 1);
+        series.fill();
+
+        let labelled: Vec<(String, u32)> = series.labelled().collect();
+        assert_eq!(labelled, vec![("2020-W53".to_owned(), 2), ("2021-W01".to_owned(), 2)]);
+
+        let mut quarters = TimeSeries::new(Granularity::Quarter);
+        quarters.record(date(2019, 11, 2), 1u32);
+        quarters.record(date(2020, 5, 17), 3);
+        quarters.fill();
+        let labelled: Vec<(String, u32)> = quarters.labelled().collect();
+        assert_eq!(labelled, vec![("2019-Q4".to_owned(), 1), ("2020-Q1".to_owned(), 0), ("2020-Q2".to_owned(), 3)]);
+    }
+}
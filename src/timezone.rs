@@ -0,0 +1,118 @@
+/*
+   Copyright 2019-2020 foamdino@gmail.com
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::fmt;
+use std::str::FromStr;
+use chrono::{DateTime, FixedOffset, LocalResult, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use git2::Time;
+use crate::{convert_git_time_to_datetime, Error};
+
+/// The timezone commits are bucketed into months, weekdays and hours in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Timezone {
+    /// The wall clock of whoever made the commit, from the offset git records.
+    #[default]
+    AuthorLocal,
+    Utc,
+    /// A named IANA zone such as `Europe/London`, following its daylight saving rules.
+    Named(Tz),
+}
+
+impl Timezone {
+
+    /// `git_time` with the offset of this timezone, so its date, weekday and
+    /// hour are the wall clock's there. An error when `git_time` is out of range.
+    pub fn local_time(&self, git_time: &Time) -> Result<DateTime<FixedOffset>, Error> {
+        let instant = convert_git_time_to_datetime(git_time)?;
+        let local = match self {
+            Timezone::AuthorLocal => match FixedOffset::east_opt(git_time.offset_minutes() * 60) {
+                Some(offset) => instant.with_timezone(&offset),
+                // an offset git shouldn't have recorded
+                None => instant.fixed_offset()
+            },
+            Timezone::Utc => instant.fixed_offset(),
+            Timezone::Named(tz) => instant.with_timezone(tz).fixed_offset(),
+        };
+        Ok(local)
+    }
+
+    /// The instants the wall clock shows `local` at in this timezone, which is
+    /// none or two around daylight saving changes. The author's own timezone
+    /// varies by commit, so it is taken to be UTC.
+    pub fn from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<DateTime<Utc>> {
+        match self {
+            Timezone::AuthorLocal | Timezone::Utc => LocalResult::Single(local.and_utc()),
+            Timezone::Named(tz) => tz.from_local_datetime(local).map(|dt| dt.with_timezone(&Utc)),
+        }
+    }
+}
+
+impl FromStr for Timezone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "author" | "local" => Ok(Timezone::AuthorLocal),
+            "utc" | "UTC" => Ok(Timezone::Utc),
+            _ => s.parse::<Tz>()
+                .map(Timezone::Named)
+                .map_err(|_| format!("unknown timezone: {} (expected author, utc or an IANA name such as Europe/London)", s))
+        }
+    }
+}
+
+impl fmt::Display for Timezone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Timezone::AuthorLocal => write!(f, "author"),
+            Timezone::Utc => write!(f, "utc"),
+            Timezone::Named(tz) => write!(f, "{}", tz.name()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Datelike, NaiveDate, Timelike, Weekday};
+
+    #[test]
+    fn test_local_time() {
+        // Monday 2020-03-30 01:30 UTC, committed from UTC-05:00
+        let git_time = Time::new(1_585_531_800, -300);
+        let author = Timezone::AuthorLocal.local_time(&git_time).unwrap();
+        assert_eq!((author.weekday(), author.hour()), (Weekday::Sun, 20));
+        let utc = Timezone::Utc.local_time(&git_time).unwrap();
+        assert_eq!((utc.weekday(), utc.hour()), (Weekday::Mon, 1));
+        // British summer time started the day before
+        let london = "Europe/London".parse::<Timezone>().unwrap().local_time(&git_time).unwrap();
+        assert_eq!(london.hour(), 2);
+        assert!("Mars/Olympus_Mons".parse::<Timezone>().is_err());
+        assert!(Timezone::Utc.local_time(&Time::new(99_999_999_999_999_999, 0)).is_err());
+    }
+
+    #[test]
+    fn test_from_local_datetime() {
+        let midnight = NaiveDate::from_ymd_opt(2020, 6, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let london = "Europe/London".parse::<Timezone>().unwrap();
+        assert_eq!(london.from_local_datetime(&midnight).single().unwrap().to_rfc3339(), "2020-05-31T23:00:00+00:00");
+        assert_eq!(Timezone::AuthorLocal.from_local_datetime(&midnight).single(), Some(midnight.and_utc()));
+        // clocks went from 01:00 to 02:00 on 2020-03-29
+        let skipped = NaiveDate::from_ymd_opt(2020, 3, 29).unwrap().and_hms_opt(1, 30, 0).unwrap();
+        assert_eq!(london.from_local_datetime(&skipped), LocalResult::None);
+    }
+}